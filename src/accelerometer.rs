@@ -1,16 +1,397 @@
-use iced::widget::canvas::Cache;
+use std::{collections::VecDeque, fmt::Display};
+
+use crate::{alarm::AlarmEvent, datasource::AccData};
 
 // functions to:
 // - calculate resultant acceleration speed
 // to be presented along the charts
 
-struct Speed2DChart {
-    cache: Cache,
+/// m/s^2 per 1 g, raw accelerometer values are in g
+pub const STANDARD_GRAVITY: f64 = 9.80665;
+
+impl AccData {
+    pub fn magnitude(&self) -> f64 {
+        (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+    }
+}
+
+/// Integrates acceleration (in g) into speed (m/s) and position (m)
+/// using the trapezoidal rule over the actual sample timestamps.
+#[derive(Debug, Clone, Default)]
+pub struct Integrator {
+    /// subtracted from every sample before integrating, in g
+    pub bias: [f64; 3],
+    prev: Option<(u64, [f64; 3])>,
+    speed: [f64; 3],
+    position: [f64; 3],
+}
+
+impl Integrator {
+    pub fn push(&mut self, timestamp: u64, acc: &AccData) {
+        let acc = [
+            (acc.x - self.bias[0]) * STANDARD_GRAVITY,
+            (acc.y - self.bias[1]) * STANDARD_GRAVITY,
+            (acc.z - self.bias[2]) * STANDARD_GRAVITY,
+        ];
+        if let Some((prev_timestamp, prev_acc)) = self.prev {
+            let dt = timestamp.saturating_sub(prev_timestamp) as f64 / 1000.;
+            for i in 0..3 {
+                let prev_speed = self.speed[i];
+                self.speed[i] += (prev_acc[i] + acc[i]) / 2. * dt;
+                self.position[i] += (prev_speed + self.speed[i]) / 2. * dt;
+            }
+        }
+        self.prev = Some((timestamp, acc));
+    }
+
+    /// Zero velocity update, for samples known to be at rest
+    pub fn zero_speed(&mut self) {
+        self.speed = [0.; 3];
+    }
+
+    pub fn speed(&self) -> [f64; 3] {
+        self.speed
+    }
+
+    pub fn position(&self) -> [f64; 3] {
+        self.position
+    }
 }
 
-struct AggregatePosition2DChart {
-    cache: Cache,
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectorConfig {
+    /// magnitude below this is free-fall, in g
+    pub free_fall_threshold: f64,
+    pub free_fall_min_ms: u64,
+    /// magnitude above this is a candidate impact, in g
+    pub impact_threshold: f64,
+    /// magnitude change rate an impact needs, in g/s
+    pub impact_min_jerk: f64,
+    /// impacts closer than this are reported once
+    pub impact_holdoff_ms: u64,
+    /// stationary needs the magnitude variance over this window below `stationary_max_variance`
+    pub stationary_window_ms: u64,
+    /// in g^2
+    pub stationary_max_variance: f64,
+    /// stationary needs the mean magnitude within this distance from 1 g
+    pub stationary_tolerance: f64,
+}
+
+impl Default for DetectorConfig {
+    fn default() -> Self {
+        Self {
+            free_fall_threshold: 0.3,
+            free_fall_min_ms: 100,
+            impact_threshold: 2.5,
+            impact_min_jerk: 20.,
+            impact_holdoff_ms: 200,
+            stationary_window_ms: 1000,
+            stationary_max_variance: 0.0005,
+            stationary_tolerance: 0.05,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MotionEvent {
+    FreeFall {
+        start: u64,
+        end: u64,
+    },
+    Impact {
+        timestamp: u64,
+        /// magnitude in g
+        peak: f64,
+        /// magnitude change rate into the impact, g/s
+        jerk: f64,
+    },
+    /// emitted when the segment ends
+    Stationary {
+        start: u64,
+        end: u64,
+        /// mean acceleration over the segment, in g
+        mean: [f64; 3],
+    },
+}
+
+impl MotionEvent {
+    /// Free-fall and impacts are reported like alarms, stationary segments are not
+    pub fn as_alarm(&self) -> Option<AlarmEvent> {
+        match *self {
+            MotionEvent::FreeFall { start, end } => Some(AlarmEvent {
+                rule: String::from("free-fall"),
+                start,
+                end: Some(end),
+                value: (end - start) as f64,
+            }),
+            MotionEvent::Impact {
+                timestamp, peak, ..
+            } => Some(AlarmEvent {
+                rule: String::from("impact"),
+                start: timestamp,
+                end: Some(timestamp),
+                value: peak,
+            }),
+            MotionEvent::Stationary { .. } => None,
+        }
+    }
+}
+
+impl Display for MotionEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MotionEvent::FreeFall { start, end } => write!(
+                f,
+                "free-fall {:.3}s - {:.3}s",
+                *start as f64 / 1000.,
+                *end as f64 / 1000.
+            ),
+            MotionEvent::Impact {
+                timestamp,
+                peak,
+                jerk,
+            } => write!(
+                f,
+                "impact at {:.3}s peak {peak:.2}g jerk {jerk:.1}g/s",
+                *timestamp as f64 / 1000.
+            ),
+            MotionEvent::Stationary { start, end, mean } => write!(
+                f,
+                "stationary {:.3}s - {:.3}s mean {:.4},{:.4},{:.4}g",
+                *start as f64 / 1000.,
+                *end as f64 / 1000.,
+                mean[0],
+                mean[1],
+                mean[2]
+            ),
+        }
+    }
+}
+
+/// Finds free-fall, impacts and stationary segments in accelerometer data (in g)
+#[derive(Debug, Clone, Default)]
+pub struct MotionDetector {
+    config: DetectorConfig,
+    prev: Option<(u64, f64)>,
+    free_fall_since: Option<u64>,
+    last_impact: Option<u64>,
+    /// timestamp, acceleration, magnitude over the stationary window
+    window: VecDeque<(u64, [f64; 3], f64)>,
+    /// start, last timestamp, sum of acceleration, sample count
+    stationary: Option<(u64, u64, [f64; 3], usize)>,
+}
+
+impl MotionDetector {
+    pub fn push(&mut self, timestamp: u64, acc: &AccData) -> Vec<MotionEvent> {
+        let c = &self.config;
+        let mut events = vec![];
+        let magnitude = acc.magnitude();
+
+        match self.free_fall_since {
+            None if magnitude < c.free_fall_threshold => self.free_fall_since = Some(timestamp),
+            Some(start) if magnitude >= c.free_fall_threshold => {
+                let end = self.prev.map_or(start, |p| p.0);
+                // timestamps can step back at a new segment
+                if end.saturating_sub(start) >= c.free_fall_min_ms {
+                    events.push(MotionEvent::FreeFall { start, end });
+                }
+                self.free_fall_since = None;
+            }
+            _ => {}
+        }
+
+        if let Some((prev_timestamp, prev_magnitude)) = self.prev {
+            let dt = timestamp.saturating_sub(prev_timestamp) as f64 / 1000.;
+            let jerk = if dt > 0. {
+                (magnitude - prev_magnitude) / dt
+            } else {
+                0.
+            };
+            let held_off = self
+                .last_impact
+                .is_some_and(|t| timestamp.saturating_sub(t) < c.impact_holdoff_ms);
+            if magnitude > c.impact_threshold && jerk > c.impact_min_jerk && !held_off {
+                events.push(MotionEvent::Impact {
+                    timestamp,
+                    peak: magnitude,
+                    jerk,
+                });
+                self.last_impact = Some(timestamp);
+            }
+        }
+
+        let value = [acc.x, acc.y, acc.z];
+        self.window.push_back((timestamp, value, magnitude));
+        while self
+            .window
+            .front()
+            .is_some_and(|w| w.0 + c.stationary_window_ms < timestamp)
+        {
+            self.window.pop_front();
+        }
+        let full = self
+            .window
+            .front()
+            .map_or(0, |w| timestamp.saturating_sub(w.0))
+            * 10
+            >= c.stationary_window_ms * 9;
+        let n = self.window.len() as f64;
+        let mean = self.window.iter().map(|w| w.2).sum::<f64>() / n;
+        let variance = self
+            .window
+            .iter()
+            .map(|w| (w.2 - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        let still = full
+            && variance <= c.stationary_max_variance
+            && (mean - 1.).abs() <= c.stationary_tolerance;
+
+        match (&mut self.stationary, still) {
+            (Some((_, last, sum, count)), true) => {
+                *last = timestamp;
+                for i in 0..3 {
+                    sum[i] += value[i];
+                }
+                *count += 1;
+            }
+            (None, true) => {
+                let mut sum = [0.; 3];
+                for (_, v, _) in &self.window {
+                    for i in 0..3 {
+                        sum[i] += v[i];
+                    }
+                }
+                self.stationary = Some((self.window[0].0, timestamp, sum, self.window.len()));
+            }
+            (Some(_), false) => {
+                let (start, end, _, _) = self.stationary.unwrap();
+                events.push(MotionEvent::Stationary {
+                    start,
+                    end,
+                    mean: self.stationary_mean().unwrap(),
+                });
+                self.stationary = None;
+            }
+            (None, false) => {}
+        }
+
+        self.prev = Some((timestamp, magnitude));
+        events
+    }
+
+    /// Mean acceleration of the ongoing stationary segment, i.e. gravity plus sensor bias
+    pub fn stationary_mean(&self) -> Option<[f64; 3]> {
+        self.stationary.map(|(_, _, sum, count)| {
+            let n = count as f64;
+            [sum[0] / n, sum[1] / n, sum[2] / n]
+        })
+    }
 }
 
 // TODO later
-struct Aggregate3DChart {}
+// struct Aggregate3DChart
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integrate_constant_acceleration() {
+        let mut integrator = Integrator::default();
+        let acc = AccData {
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        for t in 0..=10 {
+            integrator.push(t * 100, &acc);
+        }
+        // v = a*t, s = a*t^2/2 after 1s
+        assert!((integrator.speed()[0] - STANDARD_GRAVITY).abs() < 1e-9);
+        assert!((integrator.position()[0] - STANDARD_GRAVITY / 2.).abs() < 1e-9);
+        assert_eq!(integrator.speed()[1], 0.0);
+    }
+
+    #[test]
+    fn test_drift_of_a_tone() {
+        use crate::generator::{Axis, Generator, GeneratorConfig, Tone};
+        use std::f64::consts::TAU;
+
+        let (frequency_hz, amplitude_g) = (0.5, 0.05);
+        let board = GeneratorConfig {
+            tones: vec![Tone {
+                axis: Axis::X,
+                frequency_hz,
+                amplitude_g,
+            }],
+            rotation_dps: [0.; 3],
+            noise_g: 0.,
+            dropout_probability: 0.,
+            jitter_ms: 0.,
+            ..Default::default()
+        };
+        let mut integrator = Integrator {
+            bias: [0., 0., -1.],
+            ..Default::default()
+        };
+        // five periods of a sine starting at rest
+        for d in Generator::new(board).take(1001) {
+            integrator.push(d.timestamp, &d.acc);
+        }
+        // v = a/w (1 - cos wt) is back to 0, its mean a/w carries the position
+        let w = TAU * frequency_hz;
+        let drift = amplitude_g * STANDARD_GRAVITY / w * 10.;
+        assert!(
+            integrator.speed()[0].abs() < 1e-3,
+            "{:?}",
+            integrator.speed()
+        );
+        assert!((integrator.position()[0] - drift).abs() < 1e-3 * drift);
+        assert!(integrator.position()[2].abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_motion_detector() {
+        let acc = |x, y, z| AccData { x, y, z };
+        let mut detector = MotionDetector::default();
+        let mut events = vec![];
+        // 2s resting with the sensor upside down, then a drop and an impact
+        for t in 0..20 {
+            events.extend(detector.push(t * 100, &acc(0.01, -0.01, -1.0 + (t % 2) as f64 * 0.002)));
+        }
+        assert!(detector.stationary_mean().is_some());
+        for t in 20..25 {
+            events.extend(detector.push(t * 100, &acc(0., 0., -0.05)));
+        }
+        events.extend(detector.push(2500, &acc(0.5, 0.2, -4.)));
+        events.extend(detector.push(2600, &acc(0.01, -0.01, -1.)));
+
+        assert!(
+            matches!(events[0], MotionEvent::Stationary { start: 0, end: 1900, mean }
+            if (mean[2] + 0.999).abs() < 1e-9)
+        );
+        assert_eq!(
+            events[1],
+            MotionEvent::FreeFall {
+                start: 2000,
+                end: 2400
+            }
+        );
+        assert!(matches!(
+            events[2],
+            MotionEvent::Impact {
+                timestamp: 2500,
+                ..
+            }
+        ));
+        assert_eq!(events.len(), 3);
+
+        // a device reset steps back in the middle of a free-fall
+        let mut detector = MotionDetector::default();
+        for t in [5000, 100] {
+            assert!(detector.push(t, &acc(0., 0., -0.05)).is_empty());
+        }
+        assert!(detector.push(200, &acc(0., 0., -1.)).is_empty());
+    }
+}
@@ -0,0 +1,291 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    datasource::QualityEvent,
+    derived::{ChannelInfo, Row},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    Above(f64),
+    Below(f64),
+    /// outside of the (low, high) band
+    Outside(f64, f64),
+    /// no samples for longer than this many ms, not tied to a channel
+    GapLongerThan(u64),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlarmRule {
+    pub name: String,
+    /// ignored for [`Condition::GapLongerThan`]
+    #[serde(default)]
+    pub channel: String,
+    pub condition: Condition,
+    /// how far back inside the threshold a value has to get to end the alarm
+    #[serde(default)]
+    pub hysteresis: f64,
+    /// the condition has to hold this long before the alarm starts
+    #[serde(default)]
+    pub min_duration_ms: u64,
+}
+
+impl AlarmRule {
+    fn triggered(&self, value: f64) -> bool {
+        match self.condition {
+            Condition::Above(limit) => value > limit,
+            Condition::Below(limit) => value < limit,
+            Condition::Outside(low, high) => value < low || value > high,
+            Condition::GapLongerThan(_) => false,
+        }
+    }
+
+    fn released(&self, value: f64) -> bool {
+        let h = self.hysteresis;
+        match self.condition {
+            Condition::Above(limit) => value <= limit - h,
+            Condition::Below(limit) => value >= limit + h,
+            Condition::Outside(low, high) => value >= low + h && value <= high - h,
+            Condition::GapLongerThan(_) => true,
+        }
+    }
+}
+
+/// Built-in rules used until they are configured
+pub fn default_rules() -> Vec<AlarmRule> {
+    vec![
+        AlarmRule {
+            name: String::from("high acceleration"),
+            channel: String::from("acc_mag"),
+            condition: Condition::Above(3.),
+            hysteresis: 0.1,
+            min_duration_ms: 100,
+        },
+        AlarmRule {
+            name: String::from("low acceleration"),
+            channel: String::from("acc_mag"),
+            condition: Condition::Below(0.3),
+            hysteresis: 0.1,
+            min_duration_ms: 100,
+        },
+        AlarmRule {
+            name: String::from("magnetic field out of band"),
+            channel: String::from("mag_mag"),
+            condition: Condition::Outside(0.2, 0.7),
+            hysteresis: 0.02,
+            min_duration_ms: 500,
+        },
+        AlarmRule {
+            name: String::from("data gap"),
+            channel: String::new(),
+            condition: Condition::GapLongerThan(500),
+            hysteresis: 0.,
+            min_duration_ms: 0,
+        },
+    ]
+}
+
+/// Emitted once when an alarm starts (`end` is `None`) and once when it ends
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlarmEvent {
+    pub rule: String,
+    pub start: u64,
+    pub end: Option<u64>,
+    /// the most extreme value seen while triggered
+    pub value: f64,
+}
+
+impl Display for AlarmEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.end {
+            Some(end) => write!(
+                f,
+                "{:.3}s - {:.3}s {} ({:.3})",
+                self.start as f64 / 1000.,
+                end as f64 / 1000.,
+                self.rule,
+                self.value
+            ),
+            None => write!(
+                f,
+                "{:.3}s - ... {} ({:.3})",
+                self.start as f64 / 1000.,
+                self.rule,
+                self.value
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum RuleState {
+    Idle,
+    Pending { since: u64, value: f64 },
+    Active { since: u64, value: f64 },
+}
+
+/// Evaluates [`AlarmRule`]s against processed rows and quality events
+pub struct AlarmEngine {
+    /// rule, index of its channel in the rows, state
+    rules: Vec<(AlarmRule, Option<usize>, RuleState)>,
+}
+
+impl AlarmEngine {
+    /// Fails with the rule name when its channel is not one of `channels`
+    pub fn new(rules: Vec<AlarmRule>, channels: &[ChannelInfo]) -> Result<Self, String> {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                let index = match rule.condition {
+                    Condition::GapLongerThan(_) => None,
+                    _ => Some(channels.iter().position(|c| c.name == rule.channel).ok_or(
+                        format!("alarm {}: unknown channel {}", rule.name, rule.channel),
+                    )?),
+                };
+                Ok((rule, index, RuleState::Idle))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn process(&mut self, row: &Row) -> Vec<AlarmEvent> {
+        let mut events = vec![];
+        for (rule, index, state) in &mut self.rules {
+            let Some(index) = index else {
+                continue;
+            };
+            let value = row.values[*index];
+            let extreme = |prev: f64| match rule.condition {
+                Condition::Below(_) => prev.min(value),
+                Condition::Outside(low, _) if value < low => prev.min(value),
+                _ => prev.max(value),
+            };
+            *state = match *state {
+                RuleState::Idle if rule.triggered(value) => RuleState::Pending {
+                    since: row.timestamp,
+                    value,
+                },
+                RuleState::Idle => RuleState::Idle,
+                RuleState::Pending { .. } if !rule.triggered(value) => RuleState::Idle,
+                RuleState::Pending { since, value: v } => RuleState::Pending {
+                    since,
+                    value: extreme(v),
+                },
+                RuleState::Active { since, value: v } if rule.released(value) => {
+                    events.push(AlarmEvent {
+                        rule: rule.name.clone(),
+                        start: since,
+                        end: Some(row.timestamp),
+                        value: v,
+                    });
+                    RuleState::Idle
+                }
+                RuleState::Active { since, value: v } => RuleState::Active {
+                    since,
+                    value: extreme(v),
+                },
+            };
+            if let RuleState::Pending { since, value } = *state {
+                if row.timestamp.saturating_sub(since) >= rule.min_duration_ms {
+                    events.push(AlarmEvent {
+                        rule: rule.name.clone(),
+                        start: since,
+                        end: None,
+                        value,
+                    });
+                    *state = RuleState::Active { since, value };
+                }
+            }
+        }
+        events
+    }
+
+    pub fn quality_event(&mut self, event: &QualityEvent) -> Vec<AlarmEvent> {
+        let QualityEvent::Gap { start, end } = *event else {
+            return vec![];
+        };
+        self.rules
+            .iter()
+            .filter_map(|(rule, _, _)| match rule.condition {
+                Condition::GapLongerThan(limit) if end - start > limit => Some(AlarmEvent {
+                    rule: rule.name.clone(),
+                    start,
+                    end: Some(end),
+                    value: (end - start) as f64,
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hysteresis_and_min_duration() {
+        let channels = [ChannelInfo::new("acc_mag", "g")];
+        let rule = AlarmRule {
+            name: String::from("high"),
+            channel: String::from("acc_mag"),
+            condition: Condition::Above(3.),
+            hysteresis: 0.5,
+            min_duration_ms: 200,
+        };
+        let mut engine = AlarmEngine::new(vec![rule], &channels).unwrap();
+        let input = [
+            (0, 1.),
+            (100, 3.5), // too short
+            (200, 1.),
+            (300, 3.2),
+            (400, 4.),
+            (500, 3.1), // active since 300
+            (600, 2.8), // within hysteresis
+            (700, 2.4), // released
+        ];
+        let events: Vec<AlarmEvent> = input
+            .into_iter()
+            .flat_map(|(timestamp, v)| {
+                engine.process(&Row {
+                    timestamp,
+                    values: vec![v],
+                })
+            })
+            .collect();
+
+        assert_eq!(
+            events,
+            [
+                AlarmEvent {
+                    rule: String::from("high"),
+                    start: 300,
+                    end: None,
+                    value: 4.
+                },
+                AlarmEvent {
+                    rule: String::from("high"),
+                    start: 300,
+                    end: Some(700),
+                    value: 4.
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_channel() {
+        let rule = AlarmRule {
+            name: String::from("bad"),
+            channel: String::from("nope"),
+            condition: Condition::Below(0.),
+            hysteresis: 0.,
+            min_duration_ms: 0,
+        };
+        assert!(AlarmEngine::new(vec![rule], &[]).is_err());
+    }
+}
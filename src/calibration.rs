@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accelerometer::STANDARD_GRAVITY,
+    datasource::{AccData, Data, MagData},
+};
+
+/// Per-axis linear correction: `calibrated = (raw - offset) * scale`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AxisCalibration {
+    pub offset: [f64; 3],
+    pub scale: [f64; 3],
+}
+
+impl AxisCalibration {
+    pub fn apply(&self, raw: [f64; 3]) -> [f64; 3] {
+        [
+            (raw[0] - self.offset[0]) * self.scale[0],
+            (raw[1] - self.offset[1]) * self.scale[1],
+            (raw[2] - self.offset[2]) * self.scale[2],
+        ]
+    }
+}
+
+impl Default for AxisCalibration {
+    fn default() -> Self {
+        Self {
+            offset: [0.0; 3],
+            scale: [1.0; 3],
+        }
+    }
+}
+
+/// Calibration of both sensors, defaults to identity
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Calibration {
+    pub acc: AxisCalibration,
+    /// offset here is the hard-iron offset, scale the soft-iron diagonal
+    pub mag: AxisCalibration,
+}
+
+impl Calibration {
+    /// Also convert from the `units` of the source to g and gauss
+    pub fn with_units(mut self, units: Units) -> Self {
+        let (acc, mag) = units.factors();
+        self.acc.scale = self.acc.scale.map(|s| s * acc);
+        self.mag.scale = self.mag.scale.map(|s| s * mag);
+        self
+    }
+
+    pub fn apply(&self, data: &Data) -> Data {
+        let [ax, ay, az] = self.acc.apply([data.acc.x, data.acc.y, data.acc.z]);
+        let [mx, my, mz] = self.mag.apply([data.mag.x, data.mag.y, data.mag.z]);
+        Data {
+            timestamp: data.timestamp,
+            acc: AccData {
+                x: ax,
+                y: ay,
+                z: az,
+            },
+            mag: MagData {
+                x: mx,
+                y: my,
+                z: mz,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AccUnit {
+    #[default]
+    #[serde(rename = "g")]
+    G,
+    #[serde(rename = "m/s2")]
+    MetersPerSecondSquared,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MagUnit {
+    #[default]
+    #[serde(rename = "gauss")]
+    Gauss,
+    #[serde(rename = "uT")]
+    Microtesla,
+}
+
+/// Units a source reports the sensors in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Units {
+    pub acc: AccUnit,
+    pub mag: MagUnit,
+}
+
+impl Units {
+    /// Factors converting the accelerometer to g and the magnetometer to gauss
+    fn factors(self) -> (f64, f64) {
+        let acc = match self.acc {
+            AccUnit::G => 1.,
+            AccUnit::MetersPerSecondSquared => 1. / STANDARD_GRAVITY,
+        };
+        let mag = match self.mag {
+            MagUnit::Gauss => 1.,
+            MagUnit::Microtesla => 0.01,
+        };
+        (acc, mag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{Generator, GeneratorConfig};
+
+    #[test]
+    fn test_hard_iron_of_a_turning_board() {
+        // a full turn about z with a horizontal field sweeps a circle around
+        // the hard iron offset
+        let board = GeneratorConfig {
+            rotation_dps: [0., 0., 36.],
+            field_gauss: [0.2, 0., 0.],
+            noise_gauss: 0.0005,
+            dropout_probability: 0.,
+            ..Default::default()
+        };
+        let samples: Vec<Data> = Generator::new(board.clone()).take(1000).collect();
+        let axis = |d: &Data| [d.mag.x, d.mag.y, d.mag.z];
+        let mut low = [f64::MAX; 3];
+        let mut high = [f64::MIN; 3];
+        for d in &samples {
+            for (i, v) in axis(d).into_iter().enumerate() {
+                low[i] = low[i].min(v);
+                high[i] = high[i].max(v);
+            }
+        }
+        let offset = [0, 1, 2].map(|i| (low[i] + high[i]) / 2.);
+        for (found, expected) in offset.iter().zip(board.hard_iron_gauss) {
+            assert!((found - expected).abs() < 0.002, "{offset:?}");
+        }
+
+        let calibration = Calibration {
+            mag: AxisCalibration {
+                offset,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        for d in &samples {
+            let field = calibration.apply(d).mag;
+            let strength = (field.x.powi(2) + field.y.powi(2) + field.z.powi(2)).sqrt();
+            assert!((strength - 0.2).abs() < 0.005, "{strength}");
+        }
+    }
+}
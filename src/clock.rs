@@ -0,0 +1,178 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::datasource::Data;
+
+/// How the raw device timestamps, in ms since the device booted, are turned
+/// into session time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClockConfig {
+    /// start the session at 0 instead of the device uptime
+    pub rebase: bool,
+    /// width of the device counter, it wraps around to 0 after 2^bits ms
+    pub counter_bits: u32,
+    /// a timestamp further back than this is a device reset, or a
+    /// wrap-around near the end of the counter. Shorter steps back follow
+    /// the [`crate::datasource::OrderPolicy`].
+    pub reset_threshold_ms: u64,
+    /// label the axes with the wall-clock time the samples were received at
+    pub wall_clock: bool,
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        Self {
+            rebase: true,
+            counter_bits: 32,
+            reset_threshold_ms: 1000,
+            wall_clock: false,
+        }
+    }
+}
+
+/// Session time of one device. Wrap-arounds of the device counter continue
+/// the session where it was, every other step back is left to the
+/// [`crate::datasource::Sequencer`] between [`Self::unwrap`] and
+/// [`Self::rebase`].
+#[derive(Debug, Clone)]
+pub struct Clock {
+    config: ClockConfig,
+    /// added to the raw timestamps, grows with every wrap-around
+    offset: u64,
+    /// subtracted from the unwrapped timestamps
+    origin: Option<u64>,
+    prev: Option<u64>,
+    /// wall-clock ms since the epoch at session time 0
+    wall_origin: Option<u64>,
+    pub wraps: usize,
+}
+
+impl Clock {
+    pub fn new(config: ClockConfig) -> Self {
+        Self {
+            config,
+            offset: 0,
+            origin: None,
+            prev: None,
+            wall_origin: None,
+            wraps: 0,
+        }
+    }
+
+    /// The `raw` device timestamp with the wrap-arounds of the counter
+    /// undone
+    pub fn unwrap_ms(&mut self, raw: u64) -> u64 {
+        let threshold = self.config.reset_threshold_ms;
+        let period = 1u64.checked_shl(self.config.counter_bits);
+        if let (Some(prev), Some(period)) = (self.prev, period) {
+            // far back and close enough to the end of the counter to have wrapped
+            if prev > raw + threshold && prev + threshold >= period {
+                self.offset += period;
+                self.wraps += 1;
+            }
+        }
+        self.prev = Some(raw);
+        raw + self.offset
+    }
+
+    /// Session time in ms of the unwrapped timestamp `t`. The first `t` is
+    /// the session start, the ones after it have to be later, as the
+    /// sequencer passes them on.
+    pub fn rebase_ms(&mut self, t: u64) -> u64 {
+        let origin = *self
+            .origin
+            .get_or_insert(if self.config.rebase { t } else { 0 });
+        t - origin
+    }
+
+    /// `data` with the wrap-arounds undone, still in device time
+    pub fn unwrap(&mut self, mut data: Data) -> Data {
+        data.timestamp = self.unwrap_ms(data.timestamp);
+        data
+    }
+
+    /// `data` in order from the sequencer, in session time
+    pub fn rebase(&mut self, mut data: Data) -> Data {
+        data.timestamp = self.rebase_ms(data.timestamp);
+        data
+    }
+
+    /// Map session time to the wall clock, from the `timestamp` in session
+    /// time `received` at. The earliest mapping is kept, it has the least
+    /// latency in it.
+    pub fn received(&mut self, timestamp: u64, received: SystemTime) {
+        if !self.config.wall_clock {
+            return;
+        }
+        let Ok(since_epoch) = received.duration_since(UNIX_EPOCH) else {
+            return;
+        };
+        let origin = (since_epoch.as_millis() as u64).saturating_sub(timestamp);
+        self.wall_origin = Some(self.wall_origin.map_or(origin, |o| o.min(origin)));
+    }
+
+    /// Wall-clock ms since the epoch at session time 0, when known
+    pub fn wall_origin(&self) -> Option<u64> {
+        self.wall_origin
+    }
+}
+
+/// `HH:MM:SS.mmm` UTC of `ms` since the epoch
+pub fn format_wall_clock(ms: u64) -> String {
+    let day_ms = ms % 86_400_000;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        day_ms / 3_600_000,
+        day_ms / 60_000 % 60,
+        day_ms / 1000 % 60,
+        day_ms % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasource::{Anomalies, OrderPolicy, Sequencer};
+
+    /// Session times of `raw` through a clock and a sequencer, as replayed
+    fn replay(config: ClockConfig, raw: &[u64]) -> (Vec<u64>, Clock, Anomalies) {
+        let mut clock = Clock::new(config);
+        let mut sequencer = Sequencer::new(OrderPolicy::Drop, config.reset_threshold_ms);
+        let mut session = vec![];
+        for &timestamp in raw {
+            let data = clock.unwrap(Data {
+                timestamp,
+                ..Default::default()
+            });
+            for d in sequencer.push(data) {
+                session.push(clock.rebase(d).timestamp);
+            }
+        }
+        (session, clock, sequencer.anomalies)
+    }
+
+    #[test]
+    fn test_rebase_wrap_and_reset() {
+        let config = ClockConfig {
+            counter_bits: 16,
+            ..Default::default()
+        };
+        // uptime, wrap-around of the 16 bit counter, a step back, a reboot
+        let raw = [5707, 5807, 65_500, 64, 20, 164, 30_000, 30_100, 100, 200];
+        let (session, clock, anomalies) = replay(config, &raw);
+        assert_eq!(
+            session,
+            [0, 100, 59_793, 59_893, 59_993, 89_829, 89_929, 90_029, 90_129]
+        );
+        assert_eq!(clock.wraps, 1);
+        assert_eq!((anomalies.out_of_order, anomalies.resets), (1, 1));
+
+        // a step back right at the start is dropped, not put at the start
+        let (session, _, anomalies) = replay(config, &[5707, 5600, 5807]);
+        assert_eq!(session, [0, 100]);
+        assert_eq!(anomalies.out_of_order, 1);
+        assert_eq!(format_wall_clock(3_723_004), "01:02:03.004");
+    }
+}
@@ -0,0 +1,557 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    alarm::{self, AlarmRule, Condition},
+    calibration::{Calibration, Units},
+    clock::{Clock, ClockConfig},
+    datasource::{read_file, Columns, Data, OrderPolicy, Sequencer},
+    derived::Processor,
+    filter::FilterKind,
+    generator::GeneratorConfig,
+    style::{AppTheme, ChartStyle},
+};
+
+pub const CONFIG_FILE: &str = "aeroplot.toml";
+/// recordings kept in [`Config::recent`]
+const MAX_RECENT: usize = 10;
+
+/// Charts that can be placed in the layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChartId {
+    Accelerometer,
+    Spectrum,
+    Spectrogram,
+    Speed,
+    Heading,
+    Magnetometer,
+    Derived,
+    /// accelerometer and magnetometer of every other device
+    Devices,
+    /// [`Config::overlay_channel`] of every device
+    Overlay,
+    /// plotters line and 3D examples
+    Demo,
+}
+
+impl Display for ChartId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ChartId::Accelerometer => "accelerometer",
+            ChartId::Spectrum => "spectrum",
+            ChartId::Spectrogram => "spectrogram",
+            ChartId::Speed => "speed",
+            ChartId::Heading => "heading",
+            ChartId::Magnetometer => "magnetometer",
+            ChartId::Derived => "derived",
+            ChartId::Devices => "devices",
+            ChartId::Overlay => "overlay",
+            ChartId::Demo => "demo",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SourceConfig {
+    /// tags the device the recording comes from
+    pub device: String,
+    pub path: PathBuf,
+    /// samples are generated instead of read from `path` when set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generator: Option<GeneratorConfig>,
+    pub columns: Columns,
+    pub units: Units,
+    /// `"none"` passes the samples through as received
+    #[serde(with = "rate_or_none")]
+    pub resample_hz: Option<f64>,
+    pub gap_threshold_ms: u64,
+    pub clock: ClockConfig,
+    /// for timestamps going back, after the clock
+    pub order: OrderPolicy,
+    /// added to every timestamp, aligns the devices on one time axis
+    pub offset_ms: i64,
+}
+
+impl Default for SourceConfig {
+    fn default() -> Self {
+        Self {
+            device: String::from("main"),
+            path: PathBuf::from("test-input.csv"),
+            generator: None,
+            columns: Columns::default(),
+            units: Units::default(),
+            resample_hz: Some(10.),
+            gap_threshold_ms: 500,
+            clock: ClockConfig::default(),
+            order: OrderPolicy::default(),
+            offset_ms: 0,
+        }
+    }
+}
+
+impl SourceConfig {
+    /// Whole recording at `path` in session time and aligned, as it is
+    /// replayed
+    pub fn read(&self, path: &impl AsRef<Path>) -> io::Result<Vec<Data>> {
+        let mut clock = Clock::new(self.clock);
+        let mut sequencer = Sequencer::new(self.order, self.clock.reset_threshold_ms);
+        let mut ordered: Vec<Data> = read_file(path, &self.columns)?
+            .into_iter()
+            .flat_map(|d| sequencer.push(clock.unwrap(d)))
+            .collect();
+        ordered.extend(sequencer.finish());
+        Ok(ordered
+            .into_iter()
+            .filter_map(|d| self.align(clock.rebase(d)))
+            .collect())
+    }
+
+    /// `data` in session time moved onto the common time axis by
+    /// [`Self::offset_ms`], `None` when that is before the start
+    pub fn align(&self, mut data: Data) -> Option<Data> {
+        data.timestamp = data.timestamp.checked_add_signed(self.offset_ms)?;
+        Some(data)
+    }
+
+    /// Checks what parsing can not, errors start with the key below `key`
+    fn validate(&self, key: &str) -> Result<(), String> {
+        if self
+            .resample_hz
+            .is_some_and(|hz| hz <= 0. || !hz.is_finite())
+        {
+            return Err(format!("{key}.resample_hz: has to be positive"));
+        }
+        if let Some(generator) = &self.generator {
+            generator
+                .validate()
+                .map_err(|e| format!("{key}.generator.{e}"))?;
+        }
+        if !(1..=64).contains(&self.clock.counter_bits) {
+            return Err(format!("{key}.clock.counter_bits: has to be 1 to 64"));
+        }
+        if self.order == (OrderPolicy::Reorder { window: 0 }) {
+            return Err(format!("{key}.order.window: has to be positive"));
+        }
+        let columns = &self.columns;
+        let mut used = vec![columns.timestamp];
+        used.extend(columns.acc);
+        used.extend(columns.mag);
+        used.sort_unstable();
+        if let Some(w) = used.windows(2).find(|w| w[0] == w[1]) {
+            return Err(format!("{key}.columns: column {} is used twice", w[0]));
+        }
+        Ok(())
+    }
+}
+
+/// A rate in Hz, or `"none"` as TOML has no null
+mod rate_or_none {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(rate: &Option<f64>, s: S) -> Result<S::Ok, S::Error> {
+        match rate {
+            Some(hz) => s.serialize_f64(*hz),
+            None => s.serialize_str("none"),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<f64>, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Rate {
+            Hz(f64),
+            Name(String),
+        }
+        match Rate::deserialize(d) {
+            Ok(Rate::Hz(hz)) => Ok(Some(hz)),
+            Ok(Rate::Name(name)) if name == "none" => Ok(None),
+            _ => Err(D::Error::custom("expected a rate in Hz or \"none\"")),
+        }
+    }
+}
+
+/// Channel computed from a formula over the channels before it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChannelConfig {
+    pub name: String,
+    pub formula: String,
+    #[serde(default)]
+    pub unit: String,
+}
+
+impl ChannelConfig {
+    fn new(name: &str, formula: &str, unit: &str) -> Self {
+        Self {
+            name: String::from(name),
+            formula: String::from(formula),
+            unit: String::from(unit),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChartConfig {
+    /// the built-in title when left out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// only used by the time series charts
+    pub style: ChartStyle,
+}
+
+/// Charts shown side by side
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayoutRow {
+    pub charts: Vec<ChartId>,
+    /// in pixels
+    pub height: u16,
+}
+
+impl LayoutRow {
+    fn new(charts: &[ChartId], height: u16) -> Self {
+        Self {
+            charts: charts.to_vec(),
+            height,
+        }
+    }
+}
+
+/// Settings kept between sessions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub theme: AppTheme,
+    pub source: SourceConfig,
+    /// other devices replayed along with `source`
+    pub devices: Vec<SourceConfig>,
+    pub calibration: Calibration,
+    /// derived channels, the first three are charted
+    pub channels: Vec<ChannelConfig>,
+    /// chain applied to the accelerometer and magnetometer charts
+    pub filters: Vec<FilterKind>,
+    pub alarms: Vec<AlarmRule>,
+    /// channel compared between the devices on the overlay chart
+    pub overlay_channel: String,
+    pub charts: BTreeMap<ChartId, ChartConfig>,
+    /// charts left out of every row are not shown
+    pub rows: Vec<LayoutRow>,
+    /// recordings opened last, most recent first
+    pub recent: Vec<PathBuf>,
+    /// charts are redrawn at most this often, however fast the samples arrive
+    pub frame_rate_hz: u64,
+    /// samples too old for the history are written here instead of dropped,
+    /// the file is truncated at start
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history_spill: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            theme: AppTheme::default(),
+            source: SourceConfig::default(),
+            devices: vec![],
+            calibration: Calibration::default(),
+            channels: vec![
+                ChannelConfig::new("acc_norm", "sqrt(ax^2 + ay^2 + az^2)", "g"),
+                ChannelConfig::new("acc_jerk", "(acc_norm - prev(acc_norm)) / dt", "g/s"),
+                ChannelConfig::new("acc_tilt", "deg(acos(abs(az) / acc_norm))", "deg"),
+            ],
+            filters: vec![],
+            alarms: alarm::default_rules(),
+            overlay_channel: String::from("acc_z"),
+            charts: BTreeMap::new(),
+            rows: vec![
+                LayoutRow::new(&[ChartId::Accelerometer, ChartId::Spectrum], 600),
+                LayoutRow::new(&[ChartId::Spectrogram], 300),
+                LayoutRow::new(&[ChartId::Speed, ChartId::Heading], 500),
+                LayoutRow::new(&[ChartId::Magnetometer], 600),
+                LayoutRow::new(&[ChartId::Derived], 400),
+                LayoutRow::new(&[ChartId::Demo], 600),
+            ],
+            recent: vec![],
+            frame_rate_hz: 20,
+            history_spill: None,
+        }
+    }
+}
+
+impl Config {
+    /// The defaults when `path` does not exist yet. Errors name the
+    /// offending key.
+    pub fn load(path: &impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let mut config: Config = match fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("{}: {e}", path.display())),
+        };
+        config
+            .validate()
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        config.show_devices();
+        Ok(config)
+    }
+
+    /// Adds a row for the device and overlay charts when there are other
+    /// devices but `rows` leaves the charts out
+    fn show_devices(&mut self) {
+        if self.devices.is_empty() {
+            return;
+        }
+        let height = 400 * self.devices.len().min(4) as u16;
+        for (id, height) in [(ChartId::Devices, height), (ChartId::Overlay, 500)] {
+            if !self.rows.iter().any(|row| row.charts.contains(&id)) {
+                self.rows.push(LayoutRow::new(&[id], height));
+            }
+        }
+    }
+
+    pub fn save(&self, path: &impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let text = toml::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, text).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Checks what parsing can not, errors start with the offending key
+    pub fn validate(&self) -> Result<(), String> {
+        self.source.validate("source")?;
+        let mut ids = vec![&self.source.device];
+        for (i, device) in self.devices.iter().enumerate() {
+            device.validate(&format!("devices[{i}]"))?;
+            if ids.contains(&&device.device) {
+                return Err(format!(
+                    "devices[{i}].device: {:?} is used twice",
+                    device.device
+                ));
+            }
+            ids.push(&device.device);
+        }
+        for (name, axis) in [
+            ("acc", &self.calibration.acc),
+            ("mag", &self.calibration.mag),
+        ] {
+            if axis.scale.iter().any(|s| *s == 0. || !s.is_finite()) {
+                return Err(format!(
+                    "calibration.{name}.scale: has to be finite and non-zero"
+                ));
+            }
+        }
+        let processor = self.processor()?;
+        for (i, filter) in self.filters.iter().enumerate() {
+            let valid = match *filter {
+                FilterKind::MovingAverage { window_ms } => window_ms > 0,
+                FilterKind::Median { window } => window > 0,
+                FilterKind::LowPass { cutoff_hz }
+                | FilterKind::HighPass { cutoff_hz }
+                | FilterKind::ButterworthLowPass { cutoff_hz }
+                | FilterKind::ButterworthHighPass { cutoff_hz } => cutoff_hz > 0.,
+            };
+            if !valid {
+                return Err(format!("filters[{i}]: {filter} is not a valid filter"));
+            }
+        }
+        for (i, rule) in self.alarms.iter().enumerate() {
+            // the resampler only reports gaps longer than its threshold
+            if let Condition::GapLongerThan(limit) = rule.condition {
+                if limit < self.source.gap_threshold_ms {
+                    return Err(format!(
+                        "alarms[{i}].condition: a gap of {limit}ms is never reported with \
+                         source.gap_threshold_ms = {}",
+                        self.source.gap_threshold_ms
+                    ));
+                }
+            }
+            let known = processor.channels().iter().any(|c| c.name == rule.channel);
+            if !known && !matches!(rule.condition, Condition::GapLongerThan(_)) {
+                return Err(format!(
+                    "alarms[{i}].channel: unknown channel {:?}",
+                    rule.channel
+                ));
+            }
+        }
+        if !processor
+            .channels()
+            .iter()
+            .any(|c| c.name == self.overlay_channel)
+        {
+            return Err(format!(
+                "overlay_channel: unknown channel {:?}",
+                self.overlay_channel
+            ));
+        }
+        for (id, chart) in &self.charts {
+            if !ChartStyle::LINE_WIDTHS.contains(&chart.style.line_width) {
+                return Err(format!(
+                    "charts.{id}.style.line_width: has to be one of {:?}",
+                    ChartStyle::LINE_WIDTHS
+                ));
+            }
+            if chart.style.time_range_ms == 0 {
+                return Err(format!(
+                    "charts.{id}.style.time_range_ms: has to be positive"
+                ));
+            }
+        }
+        if self.frame_rate_hz == 0 || self.frame_rate_hz > 1000 {
+            return Err(String::from("frame_rate_hz: has to be 1 to 1000"));
+        }
+        if let Some(spill) = &self.history_spill {
+            if spill.file_name().is_none() {
+                return Err(format!("history_spill: {} is not a file", spill.display()));
+            }
+            if self.sources().any(|s| s.path == *spill) {
+                return Err(format!("history_spill: {} is a recording", spill.display()));
+            }
+            let dir = spill.parent().filter(|p| !p.as_os_str().is_empty());
+            if let Some(dir) = dir.filter(|dir| !dir.is_dir()) {
+                return Err(format!(
+                    "history_spill: {} is not a directory",
+                    dir.display()
+                ));
+            }
+        }
+        let mut shown = vec![];
+        for (i, row) in self.rows.iter().enumerate() {
+            for id in &row.charts {
+                if shown.contains(id) {
+                    return Err(format!("rows[{i}].charts: {id} is shown twice"));
+                }
+                shown.push(*id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Processor with the calibration, units and derived channels
+    pub fn processor(&self) -> Result<Processor, String> {
+        self.device_processor(&self.source)
+    }
+
+    /// [`Self::processor`] for the units of another device
+    pub fn device_processor(&self, source: &SourceConfig) -> Result<Processor, String> {
+        let mut processor = Processor::new(self.calibration.clone().with_units(source.units));
+        for (i, c) in self.channels.iter().enumerate() {
+            processor
+                .add_expression_channel(&c.name, &c.formula, &c.unit)
+                .map_err(|e| format!("channels[{i}]: {e}"))?;
+        }
+        Ok(processor)
+    }
+
+    /// `source` followed by the other devices
+    pub fn sources(&self) -> impl Iterator<Item = &SourceConfig> {
+        std::iter::once(&self.source).chain(&self.devices)
+    }
+
+    pub fn add_recent(&mut self, path: PathBuf) {
+        self.recent.retain(|p| *p != path);
+        self.recent.insert(0, path);
+        self.recent.truncate(MAX_RECENT);
+    }
+
+    /// Title and style of a chart, the defaults when it is not configured
+    pub fn chart(&self, id: ChartId) -> ChartConfig {
+        self.charts.get(&id).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let path = std::env::temp_dir().join("aeroplot-config-test.toml");
+        let mut config = Config {
+            theme: AppTheme::HighContrast,
+            source: SourceConfig {
+                resample_hz: None,
+                ..Default::default()
+            },
+            filters: vec![FilterKind::LowPass { cutoff_hz: 2. }],
+            ..Default::default()
+        };
+        config.charts.insert(ChartId::Speed, ChartConfig::default());
+        for path in ["a.csv", "b.csv", "a.csv"] {
+            config.add_recent(PathBuf::from(path));
+        }
+        assert_eq!(
+            config.recent,
+            [PathBuf::from("a.csv"), PathBuf::from("b.csv")]
+        );
+        config.save(&path).unwrap();
+        assert_eq!(Config::load(&path).unwrap(), config);
+
+        // the other devices are shown without listing them in `rows`
+        config.devices.push(SourceConfig {
+            device: String::from("wing"),
+            ..Default::default()
+        });
+        config.save(&path).unwrap();
+        let shown: Vec<ChartId> = Config::load(&path)
+            .unwrap()
+            .rows
+            .iter()
+            .flat_map(|row| row.charts.clone())
+            .collect();
+        assert!(shown.ends_with(&[ChartId::Devices, ChartId::Overlay]));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_errors_name_the_key() {
+        let error = |text: &str| {
+            toml::from_str::<Config>(text)
+                .map_err(|e| e.to_string())
+                .and_then(|c| c.validate().map(|_| c))
+                .unwrap_err()
+        };
+        assert!(error("[source]\npaht = \"a.csv\"").contains("paht"));
+        assert!(error("[charts.speed.style]\nline_width = 7")
+            .starts_with("charts.speed.style.line_width"));
+        assert!(
+            error("[[alarms]]\nname = \"x\"\nchannel = \"nope\"\ncondition = { above = 1.0 }")
+                .starts_with("alarms[0].channel")
+        );
+        assert!(error("[[channels]]\nname = \"y\"\nformula = \"ax +\"").starts_with("channels[0]"));
+        assert!(error("[[devices]]\ndevice = \"main\"").starts_with("devices[0].device"));
+        assert!(error("frame_rate_hz = 0").starts_with("frame_rate_hz"));
+        assert!(
+            error("[[alarms]]\nname = \"gap\"\ncondition = { gap_longer_than = 200 }")
+                .starts_with("alarms[0].condition")
+        );
+        assert!(error(
+            "[source.generator]\ntones = [{ axis = \"x\", frequency_hz = 1.0, amplitude_g = -1.0 }]"
+        )
+        .starts_with("source.generator.tones[0].amplitude_g"));
+        assert!(error("[source.generator]\ndropout_ms = 100000000000000000")
+            .starts_with("source.generator.dropout_ms"));
+        assert!(error("history_spill = \"test-input.csv\"").starts_with("history_spill"));
+        assert!(error("history_spill = \"nowhere/h.csv\"").starts_with("history_spill"));
+
+        let config: Config = toml::from_str(
+            "[source]\npath = \"other.csv\"\ncolumns = { delimiter = \";\", timestamp = 6, acc = [0, 1, 2], mag = [3, 4, 5] }",
+        )
+        .unwrap();
+        // the keys left out of a partial table keep their defaults
+        assert_eq!(config.source.resample_hz, Some(10.));
+        assert_eq!(config.source.gap_threshold_ms, 500);
+        assert!(config.validate().is_ok());
+
+        let config: Config = toml::from_str("[source]\nresample_hz = \"none\"").unwrap();
+        assert_eq!(config.source.resample_hz, None);
+        assert!(error("[source]\nresample_hz = \"fast\"").contains("resample_hz"));
+    }
+}
@@ -3,52 +3,42 @@
 use std::{
     fmt::Display,
     fs::File,
-    io::{BufRead, BufReader},
+    io::{self, BufRead, BufReader},
     path::{Path, PathBuf},
+    str::FromStr,
 };
 
+use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
-use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
+use tokio_stream::{wrappers::ReceiverStream, Stream};
 
-#[derive(Debug, Clone)]
+pub use order::{Anomalies, OrderPolicy, Sequencer};
+pub use resample::{Interpolation, QualityEvent, Resampled, Resampler};
+
+mod order;
+mod resample;
+
+#[derive(Debug, Clone, Default)]
 pub struct Data {
     pub timestamp: u64,
     pub acc: AccData,
     pub mag: MagData,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct AccData {
     pub x: f64,
     pub y: f64,
     pub z: f64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct MagData {
     pub x: f64,
     pub y: f64,
     pub z: f64,
 }
 
-impl Default for Data {
-    fn default() -> Self {
-        Self {
-            timestamp: 0,
-            acc: AccData {
-                x: 0.0,
-                y: 0.0,
-                z: 0.0,
-            },
-            mag: MagData {
-                x: 0.0,
-                y: 0.0,
-                z: 0.0,
-            },
-        }
-    }
-}
-
 impl Display for Data {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -59,60 +49,143 @@ impl Display for Data {
     }
 }
 
-impl From<&str> for Data {
-    fn from(value: &str) -> Self {
-        let mut x = value.trim().split(",");
+/// Where the fields of a sample are in a line of the input, 0 based
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Columns {
+    pub delimiter: char,
+    pub timestamp: usize,
+    pub acc: [usize; 3],
+    pub mag: [usize; 3],
+}
+
+impl Columns {
+    pub fn parse(&self, line: &str) -> Result<Data, String> {
+        let fields: Vec<&str> = line.trim().split(self.delimiter).map(str::trim).collect();
+        let field = |name: &str, i: usize| fields.get(i).copied().ok_or(format!("missing {name}"));
+        let float = |name: &str, i: usize| {
+            let v = field(name, i)?;
+            v.parse::<f64>()
+                .map_err(|e| format!("bad {name} {v:?}: {e}"))
+        };
 
-        let timestamp = x.next().unwrap().parse().unwrap();
+        let v = field("timestamp", self.timestamp)?;
+        let timestamp = v.parse().map_err(|e| format!("bad timestamp {v:?}: {e}"))?;
         let acc = AccData {
-            x: x.next().unwrap().parse().unwrap(),
-            y: x.next().unwrap().parse().unwrap(),
-            z: x.next().unwrap().parse().unwrap(),
+            x: float("acc_x", self.acc[0])?,
+            y: float("acc_y", self.acc[1])?,
+            z: float("acc_z", self.acc[2])?,
         };
         let mag = MagData {
-            x: x.next().unwrap().parse().unwrap(),
-            y: x.next().unwrap().parse().unwrap(),
-            z: x.next().unwrap().parse().unwrap(),
+            x: float("mag_x", self.mag[0])?,
+            y: float("mag_y", self.mag[1])?,
+            z: float("mag_z", self.mag[2])?,
         };
 
-        Data {
+        Ok(Data {
             timestamp,
             acc,
             mag,
+        })
+    }
+}
+
+/// `timestamp,acc_x,acc_y,acc_z,mag_x,mag_y,mag_z`
+impl Default for Columns {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            timestamp: 0,
+            acc: [1, 2, 3],
+            mag: [4, 5, 6],
         }
     }
 }
 
-pub async fn stream_file(path: &impl AsRef<Path>) -> impl Stream<Item = Data> {
-    let (tx, rx) = mpsc::channel::<Data>(10);
+impl FromStr for Data {
+    type Err = String;
+
+    /// In the [`Columns::default`] layout
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Columns::default().parse(value)
+    }
+}
+
+/// Malformed lines are reported as [`QualityEvent::MalformedLine`] and skipped
+pub async fn stream_file(
+    path: &impl AsRef<Path>,
+    columns: Columns,
+) -> impl Stream<Item = Result<Data, QualityEvent>> {
+    let (tx, rx) = mpsc::channel::<Result<Data, QualityEvent>>(10);
 
     // let x = path.to_owned();
     let p = PathBuf::from(path.as_ref());
     tokio::spawn(async move {
-        use std::io::prelude::*;
-        let file = File::open(p).unwrap();
+        let file = match File::open(&p) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("failed to open {}: {e}", p.display());
+                return;
+            }
+        };
         let reader = BufReader::new(file);
 
-        let mut prev = 0;
-        for line in reader.lines() {
+        let mut prev = None;
+        for (number, line) in reader.lines().enumerate() {
             let line = line.unwrap();
             let line = line.trim();
-            if line.len() == 0 {
+            if line.is_empty() {
                 continue;
             }
-            let data: Data = line.into();
-
-            if prev != 0 {
-                tokio::time::sleep(tokio::time::Duration::from_millis(data.timestamp - prev)).await;
+            let data = match columns.parse(line) {
+                Ok(data) => data,
+                Err(reason) => {
+                    let event = QualityEvent::MalformedLine {
+                        line: number + 1,
+                        reason,
+                    };
+                    if tx.send(Err(event)).await.is_err() {
+                        return;
+                    }
+                    continue;
+                }
+            };
+
+            // replayed in real time, no wait for timestamps going back
+            if let Some(prev) = prev {
+                let wait = data.timestamp.saturating_sub(prev);
+                tokio::time::sleep(tokio::time::Duration::from_millis(wait)).await;
+            }
+            prev = Some(data.timestamp);
+            // the receiver is dropped when another recording is opened
+            if tx.send(Ok(data)).await.is_err() {
+                return;
             }
-            prev = data.timestamp;
-            tx.send(data).await.unwrap();
         }
     });
 
     ReceiverStream::new(rx)
 }
 
+/// Read a whole recording at once, for exports and offline processing.
+/// Malformed lines are skipped with a warning.
+pub fn read_file(path: &impl AsRef<Path>, columns: &Columns) -> io::Result<Vec<Data>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut data = vec![];
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        match columns.parse(line) {
+            Ok(d) => data.push(d),
+            Err(e) => eprintln!("skipping line {}: {e}", number + 1),
+        }
+    }
+    Ok(data)
+}
+
 // TODO later stream from tailing a file or from some /dev/ttyUSB
 
 #[cfg(test)]
@@ -126,10 +199,35 @@ mod tests {
 
     #[tokio::test]
     async fn test_stream_file() {
-        let mut s = stream_file(&TEST_FILE).await;
+        let mut s = stream_file(&TEST_FILE, Columns::default()).await;
         let start = Instant::now();
         while let Some(x) = s.next().await {
             println!("received data {x:?} at {}ms", start.elapsed().as_millis());
         }
     }
+
+    #[test]
+    fn test_parse_malformed() {
+        let d: Data = "5707,+0.0076,-0.0166,-0.9835,-0.268,-0.105,-0.016"
+            .parse()
+            .unwrap();
+        assert_eq!((d.timestamp, d.acc.x, d.mag.z), (5707, 0.0076, -0.016));
+        assert_eq!(
+            "5707,+0.0076,-0.0166".parse::<Data>().unwrap_err(),
+            "missing acc_z"
+        );
+        assert!("5707,x,0,0,0,0,0"
+            .parse::<Data>()
+            .unwrap_err()
+            .starts_with("bad acc_x"));
+
+        let columns = Columns {
+            delimiter: ';',
+            timestamp: 6,
+            acc: [0, 1, 2],
+            mag: [3, 4, 5],
+        };
+        let d = columns.parse("1;2;3;4;5;6;7000").unwrap();
+        assert_eq!((d.timestamp, d.acc.x, d.mag.z), (7000, 1., 6.));
+    }
 }
@@ -0,0 +1,199 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use super::Data;
+
+/// What is done with a sample older than the one before it, by less than
+/// the reset threshold. Samples with the same timestamp as the one before
+/// are always dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "policy", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum OrderPolicy {
+    /// drop it
+    #[default]
+    Drop,
+    /// hold back `window` samples and pass them on sorted, samples older than
+    /// the ones already passed on are dropped
+    Reorder { window: usize },
+    /// start a new segment continuing after the sample before it
+    NewSegment,
+}
+
+/// Timestamp problems found by a [`Sequencer`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Anomalies {
+    pub duplicates: usize,
+    /// dropped for being older than the sample before
+    pub out_of_order: usize,
+    /// put back in order by [`OrderPolicy::Reorder`]
+    pub reordered: usize,
+    /// started by [`OrderPolicy::NewSegment`]
+    pub segments: usize,
+    /// steps back further than the reset threshold, always a new segment
+    pub resets: usize,
+}
+
+impl Display for Anomalies {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "duplicates: {}, out of order: {}, reordered: {}, new segments: {}, resets: {}",
+            self.duplicates, self.out_of_order, self.reordered, self.segments, self.resets
+        )
+    }
+}
+
+/// Passes on samples with strictly increasing timestamps, following its
+/// [`OrderPolicy`] for the ones that are not. A step back further than
+/// `reset_threshold_ms` is a device reset, continued as a new segment
+/// whatever the policy.
+pub struct Sequencer {
+    policy: OrderPolicy,
+    reset_threshold_ms: u64,
+    /// held back by [`OrderPolicy::Reorder`], sorted
+    pending: Vec<Data>,
+    /// timestamp of the last sample passed on
+    prev: Option<u64>,
+    /// newest timestamp pushed, after the offset
+    latest: Option<u64>,
+    /// added to the timestamps of new segments
+    offset: u64,
+    /// last step forward, the step assumed into a new segment
+    interval: u64,
+    pub anomalies: Anomalies,
+}
+
+impl Sequencer {
+    pub fn new(policy: OrderPolicy, reset_threshold_ms: u64) -> Self {
+        Self {
+            policy,
+            reset_threshold_ms,
+            pending: vec![],
+            prev: None,
+            latest: None,
+            offset: 0,
+            interval: 1,
+            anomalies: Anomalies::default(),
+        }
+    }
+
+    /// Samples ready to be passed on after `data`
+    pub fn push(&mut self, mut data: Data) -> Vec<Data> {
+        data.timestamp += self.offset;
+        let mut ready = vec![];
+        if let Some(latest) = self.latest {
+            if latest > data.timestamp + self.reset_threshold_ms {
+                // the samples held back are from before the reset
+                ready = self.finish();
+                self.continue_after(latest, &mut data);
+                self.anomalies.resets += 1;
+            } else if latest > data.timestamp && self.policy == OrderPolicy::NewSegment {
+                self.continue_after(latest, &mut data);
+                self.anomalies.segments += 1;
+            }
+        }
+        self.latest = self.latest.max(Some(data.timestamp));
+        ready.extend(self.order(data));
+        ready
+    }
+
+    /// Shift `data` and everything after it to one interval after `latest`
+    fn continue_after(&mut self, latest: u64, data: &mut Data) {
+        let shift = latest + self.interval - data.timestamp;
+        self.offset += shift;
+        data.timestamp += shift;
+    }
+
+    fn order(&mut self, data: Data) -> Vec<Data> {
+        match self.policy {
+            OrderPolicy::Drop | OrderPolicy::NewSegment => self.pass(data).into_iter().collect(),
+            OrderPolicy::Reorder { window } => {
+                let i = self
+                    .pending
+                    .partition_point(|d| d.timestamp <= data.timestamp);
+                if i < self.pending.len() {
+                    self.anomalies.reordered += 1;
+                }
+                self.pending.insert(i, data);
+                let ready = self.pending.len().saturating_sub(window);
+                let ready: Vec<Data> = self.pending.drain(..ready).collect();
+                ready.into_iter().filter_map(|d| self.pass(d)).collect()
+            }
+        }
+    }
+
+    /// Samples still held back, at the end of the source
+    pub fn finish(&mut self) -> Vec<Data> {
+        let pending = std::mem::take(&mut self.pending);
+        pending.into_iter().filter_map(|d| self.pass(d)).collect()
+    }
+
+    /// `data` when it is newer than the last sample passed on
+    fn pass(&mut self, data: Data) -> Option<Data> {
+        match self.prev {
+            Some(prev) if data.timestamp == prev => {
+                self.anomalies.duplicates += 1;
+                return None;
+            }
+            Some(prev) if data.timestamp < prev => {
+                self.anomalies.out_of_order += 1;
+                return None;
+            }
+            Some(prev) => self.interval = data.timestamp - prev,
+            None => {}
+        }
+        self.prev = Some(data.timestamp);
+        Some(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence(policy: OrderPolicy, timestamps: &[u64]) -> (Vec<u64>, Anomalies) {
+        let mut sequencer = Sequencer::new(policy, 1000);
+        let mut out: Vec<Data> = timestamps
+            .iter()
+            .flat_map(|&timestamp| {
+                sequencer.push(Data {
+                    timestamp,
+                    ..Default::default()
+                })
+            })
+            .collect();
+        out.extend(sequencer.finish());
+        (
+            out.iter().map(|d| d.timestamp).collect(),
+            sequencer.anomalies,
+        )
+    }
+
+    #[test]
+    fn test_policies() {
+        let input = [100, 200, 200, 400, 300, 500, 50, 600];
+
+        let (out, anomalies) = sequence(OrderPolicy::Drop, &input);
+        assert_eq!(out, [100, 200, 400, 500, 600]);
+        assert_eq!((anomalies.duplicates, anomalies.out_of_order), (1, 2));
+
+        let (out, anomalies) = sequence(OrderPolicy::Reorder { window: 2 }, &input);
+        assert_eq!(out, [100, 200, 300, 400, 500, 600]);
+        assert_eq!(anomalies.reordered, 2);
+        assert_eq!((anomalies.duplicates, anomalies.out_of_order), (1, 1));
+
+        let (out, anomalies) = sequence(OrderPolicy::NewSegment, &input);
+        assert_eq!(out, [100, 200, 400, 600, 800, 1000, 1550]);
+        assert_eq!((anomalies.duplicates, anomalies.segments), (1, 2));
+
+        // a reset continues after the samples held back, whatever the policy
+        let input = [5000, 5100, 5300, 5200, 100, 200];
+        let (out, anomalies) = sequence(OrderPolicy::Reorder { window: 2 }, &input);
+        assert_eq!(out, [5000, 5100, 5200, 5300, 5400, 5500]);
+        assert_eq!((anomalies.reordered, anomalies.resets), (1, 1));
+        let (out, anomalies) = sequence(OrderPolicy::Drop, &input);
+        assert_eq!(out, [5000, 5100, 5300, 5500, 5600]);
+        assert_eq!((anomalies.out_of_order, anomalies.resets), (1, 1));
+    }
+}
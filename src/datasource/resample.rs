@@ -0,0 +1,239 @@
+use std::collections::VecDeque;
+
+use super::{AccData, Data, MagData};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    /// cubic Hermite with tangents from the neighbouring samples,
+    /// delays the output by one sample
+    Cubic,
+}
+
+/// Data quality problems found while reading a source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QualityEvent {
+    /// no samples between the two timestamps for longer than the gap threshold
+    Gap { start: u64, end: u64 },
+    /// line of the source that could not be parsed, counting from 1
+    MalformedLine { line: usize, reason: String },
+}
+
+#[derive(Debug, Clone)]
+pub enum Resampled {
+    Data(Data),
+    Event(QualityEvent),
+}
+
+/// Turns jittery samples into evenly spaced ones and reports gaps.
+///
+/// Nothing is interpolated across a gap, the output grid restarts at the
+/// first sample after it. With no rate the samples only go through gap
+/// detection.
+pub struct Resampler {
+    period_ms: Option<f64>,
+    interpolation: Interpolation,
+    gap_threshold_ms: u64,
+    /// last up to 4 input samples since the latest gap
+    history: VecDeque<Data>,
+    next_timestamp: f64,
+}
+
+impl Resampler {
+    pub fn new(rate_hz: Option<f64>, interpolation: Interpolation, gap_threshold_ms: u64) -> Self {
+        Self {
+            period_ms: rate_hz.map(|r| 1000. / r),
+            interpolation,
+            gap_threshold_ms,
+            history: VecDeque::with_capacity(4),
+            next_timestamp: 0.,
+        }
+    }
+
+    /// Feed the next input sample, samples not newer than the previous one are dropped
+    pub fn push(&mut self, data: Data) -> Vec<Resampled> {
+        let mut out = vec![];
+        if let Some(prev) = self.history.back() {
+            if data.timestamp <= prev.timestamp {
+                return out;
+            }
+            if data.timestamp - prev.timestamp > self.gap_threshold_ms {
+                let start = prev.timestamp;
+                // finish the segment before the gap without a right neighbour
+                self.emit_until(&mut out, self.history.len() - 1, true);
+                out.push(Resampled::Event(QualityEvent::Gap {
+                    start,
+                    end: data.timestamp,
+                }));
+                self.history.clear();
+            }
+        }
+
+        if self.history.is_empty() {
+            self.next_timestamp = data.timestamp as f64;
+        }
+        if self.history.len() == 4 {
+            self.history.pop_front();
+        }
+        self.history.push_back(data.clone());
+
+        if self.period_ms.is_none() {
+            out.push(Resampled::Data(data));
+            return out;
+        }
+        let lookahead = match self.interpolation {
+            Interpolation::Linear => 0,
+            Interpolation::Cubic => 1,
+        };
+        if self.history.len() > lookahead + 1 {
+            self.emit_until(&mut out, self.history.len() - 1 - lookahead, false);
+        }
+        out
+    }
+
+    /// Emit grid points up to the history sample at `end`,
+    /// including its timestamp when `inclusive`
+    fn emit_until(&mut self, out: &mut Vec<Resampled>, end: usize, inclusive: bool) {
+        let Some(period) = self.period_ms else {
+            return;
+        };
+        let end_timestamp = self.history[end].timestamp as f64;
+        while self.next_timestamp < end_timestamp
+            || (inclusive && self.next_timestamp <= end_timestamp)
+        {
+            let t = self.next_timestamp;
+            let i = (1..=end)
+                .find(|&i| self.history[i].timestamp as f64 >= t)
+                .unwrap_or(end);
+            out.push(Resampled::Data(self.interpolate(i, t)));
+            self.next_timestamp += period;
+        }
+    }
+
+    /// Value at `t` between history samples `i - 1` and `i`
+    fn interpolate(&self, i: usize, t: f64) -> Data {
+        let h = &self.history;
+        if i == 0 {
+            let mut d = h[0].clone();
+            d.timestamp = t.round() as u64;
+            return d;
+        }
+        let (p1, p2) = (&h[i - 1], &h[i]);
+        let (t1, t2) = (p1.timestamp as f64, p2.timestamp as f64);
+        let span = t2 - t1;
+        let s = ((t - t1) / span).clamp(0., 1.);
+
+        let interpolate = |f: fn(&Data) -> f64| -> f64 {
+            let (v1, v2) = (f(p1), f(p2));
+            match self.interpolation {
+                Interpolation::Linear => v1 + (v2 - v1) * s,
+                Interpolation::Cubic => {
+                    let slope = |a: &Data, b: &Data| {
+                        (f(b) - f(a)) / (b.timestamp as f64 - a.timestamp as f64)
+                    };
+                    let m1 = if i >= 2 {
+                        slope(&h[i - 2], p2)
+                    } else {
+                        slope(p1, p2)
+                    };
+                    let m2 = if i + 1 < h.len() {
+                        slope(p1, &h[i + 1])
+                    } else {
+                        slope(p1, p2)
+                    };
+                    let (s2, s3) = (s * s, s * s * s);
+                    (2. * s3 - 3. * s2 + 1.) * v1
+                        + (s3 - 2. * s2 + s) * span * m1
+                        + (-2. * s3 + 3. * s2) * v2
+                        + (s3 - s2) * span * m2
+                }
+            }
+        };
+
+        Data {
+            timestamp: t.round() as u64,
+            acc: AccData {
+                x: interpolate(|d| d.acc.x),
+                y: interpolate(|d| d.acc.y),
+                z: interpolate(|d| d.acc.z),
+            },
+            mag: MagData {
+                x: interpolate(|d| d.mag.x),
+                y: interpolate(|d| d.mag.y),
+                z: interpolate(|d| d.mag.z),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(timestamp: u64, value: f64) -> Data {
+        let mut d = Data {
+            timestamp,
+            ..Default::default()
+        };
+        d.acc.x = value;
+        d
+    }
+
+    fn resample(resampler: &mut Resampler, input: &[(u64, f64)]) -> Vec<Resampled> {
+        input
+            .iter()
+            .flat_map(|&(t, v)| resampler.push(sample(t, v)))
+            .collect()
+    }
+
+    #[test]
+    fn test_linear_resampling() {
+        let mut r = Resampler::new(Some(10.), Interpolation::Linear, 500);
+        let out = resample(
+            &mut r,
+            &[(1000, 0.), (1107, 1.07), (1215, 2.15), (1322, 3.22)],
+        );
+        let data: Vec<(u64, f64)> = out
+            .iter()
+            .filter_map(|r| match r {
+                Resampled::Data(d) => Some((d.timestamp, d.acc.x)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            data.iter().map(|d| d.0).collect::<Vec<_>>(),
+            [1000, 1100, 1200, 1300]
+        );
+        for (t, v) in data {
+            assert!((v - (t - 1000) as f64 / 100.).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_cubic_keeps_straight_lines() {
+        let mut r = Resampler::new(Some(5.), Interpolation::Cubic, 500);
+        let out = resample(&mut r, &[(0, 0.), (190, 1.9), (550, 5.5), (800, 8.)]);
+        for r in out {
+            let Resampled::Data(d) = r else { panic!() };
+            assert!((d.acc.x - d.timestamp as f64 / 100.).abs() < 1e-9, "{d}");
+        }
+    }
+
+    #[test]
+    fn test_gap_breaks_the_grid() {
+        let mut r = Resampler::new(Some(10.), Interpolation::Linear, 500);
+        let out = resample(&mut r, &[(0, 0.), (100, 1.), (1250, 2.), (1350, 3.)]);
+        let timestamps: Vec<u64> = out
+            .iter()
+            .map(|r| match r {
+                Resampled::Data(d) => d.timestamp,
+                Resampled::Event(QualityEvent::Gap { start, end }) => {
+                    assert_eq!((*start, *end), (100, 1250));
+                    u64::MAX
+                }
+                Resampled::Event(e) => panic!("unexpected {e:?}"),
+            })
+            .collect();
+        assert_eq!(timestamps, [0, 100, u64::MAX, 1250]);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{
+    accelerometer::{Integrator, MotionDetector, MotionEvent},
+    calibration::Calibration,
+    datasource::Data,
+    expression::{Context, Formula},
+    filter::{Filter, FilterChain, FilterKind},
+};
+
+/// Name and unit of a single column produced by the [`Processor`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelInfo {
+    pub name: String,
+    pub unit: String,
+}
+
+impl ChannelInfo {
+    pub fn new(name: &str, unit: &str) -> Self {
+        Self {
+            name: String::from(name),
+            unit: String::from(unit),
+        }
+    }
+}
+
+/// Calibrated and derived values of one sample, ordered as [`Processor::channels`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub timestamp: u64,
+    pub values: Vec<f64>,
+}
+
+/// Channel computed after the built-in ones, in the order they were added
+enum Extra {
+    /// source channel index and the chain producing `<source>_filtered`
+    Filtered(usize, FilterChain),
+    Expression(Formula),
+}
+
+/// Turns raw [`Data`] into calibrated values and derived channels
+/// (magnitudes, heading, integrated speed and position).
+///
+/// Stateful, samples have to be pushed in timestamp order. Stationary
+/// segments found by the motion detector keep the integration bias updated
+/// and hold the speed at zero.
+pub struct Processor {
+    calibration: Calibration,
+    integrator: Integrator,
+    detector: MotionDetector,
+    motion_events: Vec<MotionEvent>,
+    channels: Vec<ChannelInfo>,
+    extra: Vec<Extra>,
+    /// timestamps of the first and the previous sample
+    timestamps: Option<(u64, u64)>,
+}
+
+impl Processor {
+    pub fn new(calibration: Calibration) -> Self {
+        let channels = [
+            ("acc_x", "g"),
+            ("acc_y", "g"),
+            ("acc_z", "g"),
+            ("acc_mag", "g"),
+            ("mag_x", "gauss"),
+            ("mag_y", "gauss"),
+            ("mag_z", "gauss"),
+            ("mag_mag", "gauss"),
+            ("heading", "deg"),
+            ("speed_x", "m/s"),
+            ("speed_y", "m/s"),
+            ("speed_z", "m/s"),
+            ("pos_x", "m"),
+            ("pos_y", "m"),
+            ("pos_z", "m"),
+        ]
+        .into_iter()
+        .map(|(name, unit)| ChannelInfo::new(name, unit))
+        .collect();
+
+        Self {
+            calibration,
+            integrator: Integrator::default(),
+            detector: MotionDetector::default(),
+            motion_events: vec![],
+            channels,
+            extra: vec![],
+            timestamps: None,
+        }
+    }
+
+    /// Add a `<source>_filtered` channel, returns false for an unknown source
+    pub fn add_filtered_channel(&mut self, source: &str, kinds: &[FilterKind]) -> bool {
+        let Some(index) = self.channels.iter().position(|c| c.name == source) else {
+            return false;
+        };
+        let unit = self.channels[index].unit.clone();
+        self.channels
+            .push(ChannelInfo::new(&format!("{source}_filtered"), &unit));
+        self.extra
+            .push(Extra::Filtered(index, FilterChain::new(kinds)));
+        true
+    }
+
+    /// Add a channel computed by a [`Formula`], which can use every channel
+    /// added before it. Fails on a taken name or an invalid formula.
+    pub fn add_expression_channel(
+        &mut self,
+        name: &str,
+        formula: &str,
+        unit: &str,
+    ) -> Result<(), String> {
+        if self.channels.iter().any(|c| c.name == name) {
+            return Err(format!("channel {name} already exists"));
+        }
+        let formula =
+            Formula::parse(formula, &self.channels).map_err(|e| format!("{name}: {e}"))?;
+        self.channels.push(ChannelInfo::new(name, unit));
+        self.extra.push(Extra::Expression(formula));
+        Ok(())
+    }
+
+    /// Motion events found since the last call
+    pub fn take_motion_events(&mut self) -> Vec<MotionEvent> {
+        std::mem::take(&mut self.motion_events)
+    }
+
+    /// Current integration bias in g, gravity included
+    pub fn acc_bias(&self) -> [f64; 3] {
+        self.integrator.bias
+    }
+
+    pub fn channels(&self) -> &[ChannelInfo] {
+        &self.channels
+    }
+
+    pub fn process(&mut self, data: &Data) -> Row {
+        let d = self.calibration.apply(data);
+        self.motion_events
+            .extend(self.detector.push(d.timestamp, &d.acc));
+        let stationary = self.detector.stationary_mean();
+        if let Some(bias) = stationary {
+            self.integrator.bias = bias;
+        }
+        self.integrator.push(d.timestamp, &d.acc);
+        if stationary.is_some() {
+            self.integrator.zero_speed();
+        }
+        let speed = self.integrator.speed();
+        let position = self.integrator.position();
+
+        let mut values = vec![
+            d.acc.x,
+            d.acc.y,
+            d.acc.z,
+            d.acc.magnitude(),
+            d.mag.x,
+            d.mag.y,
+            d.mag.z,
+            d.mag.magnitude(),
+            d.mag.heading(),
+            speed[0],
+            speed[1],
+            speed[2],
+            position[0],
+            position[1],
+            position[2],
+        ];
+        let (first, prev) = self.timestamps.unwrap_or((d.timestamp, d.timestamp));
+        self.timestamps = Some((first, d.timestamp));
+        for extra in &mut self.extra {
+            let value = match extra {
+                Extra::Filtered(source, chain) => chain.apply(d.timestamp, values[*source]),
+                Extra::Expression(formula) => formula.eval(&Context {
+                    values: &values,
+                    t: d.timestamp.saturating_sub(first) as f64 / 1000.,
+                    dt: d.timestamp.saturating_sub(prev) as f64 / 1000.,
+                }),
+            };
+            values.push(value);
+        }
+
+        Row {
+            timestamp: d.timestamp,
+            values,
+        }
+    }
+}
+
+impl Default for Processor {
+    fn default() -> Self {
+        Self::new(Calibration::default())
+    }
+}
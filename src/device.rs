@@ -0,0 +1,179 @@
+use crate::{
+    clock::Clock,
+    config::{Config, SourceConfig},
+    datasource::{Anomalies, Data, Interpolation, QualityEvent, Resampled, Resampler, Sequencer},
+    derived::{Processor, Row},
+    filter::FilterKind,
+    generic::TimeSeriesChart,
+    history::History,
+};
+
+/// rows kept per device
+const DEVICE_HISTORY: usize = 36_000;
+
+/// Recording replayed along with the main source, with its own processing
+/// and chart group
+pub struct Device {
+    pub source: SourceConfig,
+    clock: Clock,
+    sequencer: Sequencer,
+    resampler: Resampler,
+    processor: Processor,
+    pub rows: History<Row>,
+    pub acc_chart: TimeSeriesChart,
+    pub mag_chart: TimeSeriesChart,
+    /// position among all devices, the main source is 0
+    index: usize,
+}
+
+impl Device {
+    pub fn new(index: usize, source: SourceConfig, config: &Config) -> Result<Self, String> {
+        let processor = config.device_processor(&source)?;
+        let channel = |name: &str| {
+            processor
+                .channels()
+                .iter()
+                .position(|c| c.name == name)
+                .expect("built-in channel")
+        };
+        let acc_chart = TimeSeriesChart::xyz(
+            &format!("{} accelerometer", source.device),
+            ["acc_x", "acc_y", "acc_z"].map(channel),
+        )
+        .on_device(index);
+        let mag_chart = TimeSeriesChart::xyz(
+            &format!("{} magnetometer", source.device),
+            ["mag_x", "mag_y", "mag_z"].map(channel),
+        )
+        .on_device(index);
+        Ok(Self {
+            resampler: Resampler::new(
+                source.resample_hz,
+                Interpolation::Linear,
+                source.gap_threshold_ms,
+            ),
+            clock: Clock::new(source.clock),
+            sequencer: Sequencer::new(source.order, source.clock.reset_threshold_ms),
+            source,
+            processor,
+            rows: History::new(DEVICE_HISTORY),
+            acc_chart,
+            mag_chart,
+            index,
+        })
+    }
+
+    pub fn charts(&mut self) -> [&mut TimeSeriesChart; 2] {
+        [&mut self.acc_chart, &mut self.mag_chart]
+    }
+
+    /// Overlay `kinds` on the charts that show the filtered series
+    pub fn set_filter(&mut self, kinds: &[FilterKind]) {
+        let rows = |device: usize| (device == self.index).then_some(&self.rows);
+        self.acc_chart.set_filter(kinds, rows);
+        self.mag_chart.set_filter(kinds, rows);
+    }
+
+    pub fn anomalies(&self) -> Anomalies {
+        self.sequencer.anomalies
+    }
+
+    /// Unwrap, order, rebase, align, resample and process `data`, returns the
+    /// new rows
+    pub fn push(&mut self, data: Data) -> Vec<Row> {
+        let ordered = self.sequencer.push(self.clock.unwrap(data));
+        self.process(ordered)
+    }
+
+    /// Process the samples still held back, at the end of the recording
+    pub fn finish(&mut self) -> Vec<Row> {
+        let ordered = self.sequencer.finish();
+        self.process(ordered)
+    }
+
+    fn process(&mut self, ordered: Vec<Data>) -> Vec<Row> {
+        let mut rows = vec![];
+        let resampled: Vec<Resampled> = ordered
+            .into_iter()
+            .filter_map(|d| self.source.align(self.clock.rebase(d)))
+            .flat_map(|d| self.resampler.push(d))
+            .collect();
+        for r in resampled {
+            match r {
+                Resampled::Data(d) => {
+                    let row = self.processor.process(&d);
+                    // motion events are only detected on the main source
+                    self.processor.take_motion_events();
+                    let index = self.index;
+                    for chart in self.charts() {
+                        chart.push_row(index, &row);
+                    }
+                    self.rows.push(row.clone());
+                    rows.push(row);
+                }
+                Resampled::Event(QualityEvent::Gap { start, end }) => {
+                    for chart in self.charts() {
+                        chart.push_gap(start, end);
+                    }
+                }
+                Resampled::Event(QualityEvent::MalformedLine { .. }) => {}
+            }
+        }
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasource::OrderPolicy;
+
+    #[test]
+    fn test_offset_aligns_rows() {
+        let source = SourceConfig {
+            device: String::from("wing"),
+            resample_hz: None,
+            offset_ms: -250,
+            ..Default::default()
+        };
+        let mut device = Device::new(1, source, &Config::default()).unwrap();
+        let timestamps: Vec<u64> = [100, 1000, 1100]
+            .into_iter()
+            .flat_map(|timestamp| {
+                device.push(Data {
+                    timestamp,
+                    ..Default::default()
+                })
+            })
+            .map(|row| row.timestamp)
+            .collect();
+        // rebased to the first sample, then moved back by the offset, the
+        // first one to before the start
+        assert_eq!(timestamps, [650, 750]);
+    }
+
+    #[test]
+    fn test_finish_flushes_reordered_samples() {
+        let source = SourceConfig {
+            device: String::from("wing"),
+            resample_hz: None,
+            order: OrderPolicy::Reorder { window: 2 },
+            ..Default::default()
+        };
+        let mut device = Device::new(1, source, &Config::default()).unwrap();
+        let pushed: Vec<u64> = [0, 200, 100, 300]
+            .into_iter()
+            .flat_map(|timestamp| {
+                device.push(Data {
+                    timestamp,
+                    ..Default::default()
+                })
+            })
+            .map(|row| row.timestamp)
+            .collect();
+        assert_eq!(pushed, [0, 100]);
+        let finished: Vec<u64> = device.finish().iter().map(|row| row.timestamp).collect();
+        assert_eq!(finished, [200, 300]);
+        assert_eq!(device.rows.len(), 4);
+    }
+}
@@ -0,0 +1,112 @@
+/// Keep the first, lowest, highest and last point of each of `buckets`
+/// equal slices of `x_range` (M4). Drawn `buckets` pixels wide the line
+/// looks the same as with every point, spikes included.
+///
+/// `points` have to be sorted by x.
+pub fn min_max(points: &[(f64, f64)], x_range: (f64, f64), buckets: usize) -> Vec<(f64, f64)> {
+    let span = x_range.1 - x_range.0;
+    if points.len() <= 4 * buckets || buckets == 0 || span <= 0. {
+        return points.to_vec();
+    }
+    let bucket = |x: f64| (((x - x_range.0) / span * buckets as f64) as usize).min(buckets - 1);
+
+    let mut out = Vec::with_capacity(4 * buckets);
+    for chunk in points.chunk_by(|a, b| bucket(a.0) == bucket(b.0)) {
+        let (first, last) = (0, chunk.len() - 1);
+        let (mut low, mut high) = (first, first);
+        for (i, p) in chunk.iter().enumerate() {
+            if p.1 < chunk[low].1 {
+                low = i;
+            }
+            if p.1 > chunk[high].1 {
+                high = i;
+            }
+        }
+        let mut keep = [first, low, high, last];
+        keep.sort_unstable();
+        let mut prev = None;
+        for i in keep {
+            if prev != Some(i) {
+                out.push(chunk[i]);
+                prev = Some(i);
+            }
+        }
+    }
+    out
+}
+
+/// Largest-Triangle-Three-Buckets, keeps `threshold` points that best
+/// preserve the visual shape of the series. `points` have to be sorted by x.
+pub fn lttb(points: &[(f64, f64)], threshold: usize) -> Vec<(f64, f64)> {
+    if threshold >= points.len() || threshold < 3 {
+        return points.to_vec();
+    }
+    let every = (points.len() - 2) as f64 / (threshold - 2) as f64;
+    let mut out = Vec::with_capacity(threshold);
+    let mut a = 0;
+    out.push(points[0]);
+    for i in 0..threshold - 2 {
+        let start = (i as f64 * every) as usize + 1;
+        let end = ((i + 1) as f64 * every) as usize + 1;
+        // average of the next bucket, the last point for the last bucket
+        let next = &points[end..(((i + 2) as f64 * every) as usize + 1).min(points.len())];
+        let (avg_x, avg_y) = if next.is_empty() {
+            points[points.len() - 1]
+        } else {
+            let n = next.len() as f64;
+            let (sx, sy) = next
+                .iter()
+                .fold((0., 0.), |(sx, sy), p| (sx + p.0, sy + p.1));
+            (sx / n, sy / n)
+        };
+
+        let (ax, ay) = points[a];
+        let area = |p: &(f64, f64)| ((ax - avg_x) * (p.1 - ay) - (ax - p.0) * (avg_y - ay)).abs();
+        a = (start..end)
+            .max_by(|&i, &j| area(&points[i]).total_cmp(&area(&points[j])))
+            .unwrap_or(start);
+        out.push(points[a]);
+    }
+    out.push(points[points.len() - 1]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_min_max_keeps_spikes() {
+        let mut points: Vec<(f64, f64)> = (0..10_000).map(|i| (i as f64, 0.)).collect();
+        points[5_123].1 = 7.;
+        points[8_000].1 = -3.;
+        let out = min_max(&points, (0., 10_000.), 100);
+
+        assert!(out.len() <= 400);
+        assert!(out.contains(&(5_123., 7.)));
+        assert!(out.contains(&(8_000., -3.)));
+        assert!(out.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!((out[0], out[out.len() - 1]), (points[0], points[9_999]));
+    }
+
+    #[test]
+    fn test_lttb() {
+        let points: Vec<(f64, f64)> = (0..1_000)
+            .map(|i| {
+                (
+                    i as f64,
+                    if i == 500 {
+                        10.
+                    } else {
+                        (i as f64 / 50.).sin()
+                    },
+                )
+            })
+            .collect();
+        let out = lttb(&points, 50);
+
+        assert_eq!(out.len(), 50);
+        assert!(out.contains(&(500., 10.)));
+        assert_eq!((out[0], out[49]), (points[0], points[999]));
+    }
+}
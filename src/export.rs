@@ -0,0 +1,293 @@
+use std::{
+    io::{self, Write},
+    ops::RangeInclusive,
+    path::Path,
+};
+
+use crate::{
+    datasource::Data,
+    derived::{ChannelInfo, Processor, Row},
+    marker::Marker,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    /// one JSON object per line, the first line holds the units
+    JsonLines,
+}
+
+impl ExportFormat {
+    /// Guess the format from the file extension, CSV unless `.json`/`.jsonl`
+    pub fn from_path(path: &impl AsRef<Path>) -> Self {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("json") | Some("jsonl") => Self::JsonLines,
+            _ => Self::Csv,
+        }
+    }
+}
+
+/// Run `data` through `processor` and write the samples within `range`
+/// (device timestamps in ms). Samples before the range are still processed
+/// so integrated channels start from the right state.
+///
+/// With `markers` (sorted by timestamp) a `marker` column holds their
+/// labels on the first row at or after them.
+///
+/// Returns the number of rows written.
+pub fn export<W: Write>(
+    data: &[Data],
+    range: RangeInclusive<u64>,
+    processor: &mut Processor,
+    markers: &[Marker],
+    format: ExportFormat,
+    mut out: W,
+) -> io::Result<usize> {
+    let channels = processor.channels().to_vec();
+    let with_markers = !markers.is_empty();
+    match format {
+        ExportFormat::Csv => write_csv_header(&mut out, &channels, with_markers)?,
+        ExportFormat::JsonLines => write_json_header(&mut out, &channels)?,
+    }
+
+    let mut written = 0;
+    let mut markers = markers.iter().peekable();
+    for d in data {
+        let row = processor.process(d);
+        if d.timestamp > *range.end() {
+            break;
+        }
+        let mut labels = vec![];
+        while let Some(m) = markers.next_if(|m| m.timestamp <= d.timestamp) {
+            labels.push(m.label.as_str());
+        }
+        if !range.contains(&d.timestamp) {
+            continue;
+        }
+        let label = with_markers.then(|| labels.join("; "));
+        match format {
+            ExportFormat::Csv => write_csv_row(&mut out, &row, label.as_deref())?,
+            ExportFormat::JsonLines => {
+                write_json_row(&mut out, &channels, &row, label.filter(|l| !l.is_empty()))?
+            }
+        }
+        written += 1;
+    }
+    out.flush()?;
+    Ok(written)
+}
+
+fn write_csv_header<W: Write>(
+    out: &mut W,
+    channels: &[ChannelInfo],
+    with_markers: bool,
+) -> io::Result<()> {
+    write!(out, "timestamp [ms]")?;
+    for c in channels {
+        write!(out, ",{}", csv_field(&format!("{} [{}]", c.name, c.unit)))?;
+    }
+    if with_markers {
+        write!(out, ",marker")?;
+    }
+    writeln!(out)
+}
+
+fn write_csv_row<W: Write>(out: &mut W, row: &Row, marker: Option<&str>) -> io::Result<()> {
+    write!(out, "{}", row.timestamp)?;
+    for v in &row.values {
+        if v.is_finite() {
+            write!(out, ",{v}")?;
+        } else {
+            write!(out, ",")?;
+        }
+    }
+    if let Some(label) = marker {
+        write!(out, ",{}", csv_field(label))?;
+    }
+    writeln!(out)
+}
+
+/// `field` quoted when it holds a delimiter, quote or line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        String::from(field)
+    }
+}
+
+fn write_json_header<W: Write>(out: &mut W, channels: &[ChannelInfo]) -> io::Result<()> {
+    write!(out, "{{\"units\":{{\"timestamp\":\"ms\"")?;
+    for c in channels {
+        write!(out, ",{}:{}", json_string(&c.name), json_string(&c.unit))?;
+    }
+    writeln!(out, "}}}}")
+}
+
+fn write_json_row<W: Write>(
+    out: &mut W,
+    channels: &[ChannelInfo],
+    row: &Row,
+    marker: Option<String>,
+) -> io::Result<()> {
+    write!(out, "{{\"timestamp\":{}", row.timestamp)?;
+    for (c, v) in channels.iter().zip(&row.values) {
+        let name = json_string(&c.name);
+        if v.is_finite() {
+            write!(out, ",{name}:{v}")?;
+        } else {
+            write!(out, ",{name}:null")?;
+        }
+    }
+    if let Some(label) = marker {
+        write!(out, ",\"marker\":{}", json_string(&label))?;
+    }
+    writeln!(out, "}}")
+}
+
+/// `s` as a quoted JSON string
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c < ' ' => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_range() {
+        let data: Vec<Data> = [
+            "5707,+0.0076,-0.0166,-0.9835,-0.268,-0.105,-0.016",
+            "5814,+0.0068,-0.0145,-0.9868,-0.267,-0.101,-0.015",
+            "5922,+0.0077,-0.0156,-0.9868,-0.268,-0.101,-0.014",
+        ]
+        .into_iter()
+        .map(|l| l.parse().unwrap())
+        .collect();
+
+        let mut out = vec![];
+        let written = export(
+            &data,
+            5800..=6000,
+            &mut Processor::default(),
+            &[],
+            ExportFormat::Csv,
+            &mut out,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(written, 2);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("timestamp [ms],acc_x [g],"));
+        assert!(lines[1].starts_with("5814,0.0068,"));
+
+        let markers = [
+            Marker {
+                timestamp: 5000,
+                label: String::from("engine start"),
+            },
+            Marker {
+                timestamp: 5900,
+                label: String::from("takeoff, \"rotate\""),
+            },
+        ];
+        let mut out = vec![];
+        export(
+            &data,
+            0..=u64::MAX,
+            &mut Processor::default(),
+            &markers,
+            ExportFormat::JsonLines,
+            &mut out,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(lines[0].starts_with("{\"units\":{\"timestamp\":\"ms\",\"acc_x\":\"g\""));
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].ends_with(",\"marker\":\"engine start\"}"));
+        assert!(!lines[2].contains("marker"));
+        assert!(lines[3].ends_with(",\"marker\":\"takeoff, \\\"rotate\\\"\"}"));
+
+        let mut out = vec![];
+        export(
+            &data,
+            5800..=6000,
+            &mut Processor::default(),
+            &markers,
+            ExportFormat::Csv,
+            &mut out,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(lines[0].ends_with(",marker"));
+        assert!(lines[1].ends_with(','));
+        assert!(lines[2].ends_with(",\"takeoff, \"\"rotate\"\"\""));
+    }
+
+    #[test]
+    fn test_escaped_names() {
+        assert_eq!(json_string("a\"b\\c\nd\u{1}"), r#""a\"b\\c\nd\u0001""#);
+
+        let mut processor = Processor::default();
+        processor
+            .add_expression_channel("ratio \"x,y\"", "ax / ay", "")
+            .unwrap();
+        let data = [Data::default()];
+        let mut out = vec![];
+        export(
+            &data,
+            0..=u64::MAX,
+            &mut processor,
+            &[],
+            ExportFormat::Csv,
+            &mut out,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out
+            .lines()
+            .next()
+            .unwrap()
+            .ends_with(",\"ratio \"\"x,y\"\" []\""));
+
+        let mut out = vec![];
+        export(
+            &data,
+            0..=u64::MAX,
+            &mut processor,
+            &[],
+            ExportFormat::JsonLines,
+            &mut out,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out
+            .lines()
+            .next()
+            .unwrap()
+            .ends_with(",\"ratio \\\"x,y\\\"\":\"\"}}"));
+        assert!(out
+            .lines()
+            .nth(1)
+            .unwrap()
+            .ends_with(",\"ratio \\\"x,y\\\"\":null}"));
+    }
+}
@@ -0,0 +1,404 @@
+use crate::derived::ChannelInfo;
+
+/// Short names for the raw channels, `ax` is `acc_x` and so on
+const ALIASES: [(&str, &str); 6] = [
+    ("ax", "acc_x"),
+    ("ay", "acc_y"),
+    ("az", "acc_z"),
+    ("mx", "mag_x"),
+    ("my", "mag_y"),
+    ("mz", "mag_z"),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+    Open,
+    Close,
+    Comma,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+    Sqrt,
+    Abs,
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Atan2,
+    Exp,
+    Ln,
+    Log10,
+    Pow,
+    Min,
+    Max,
+    Hypot,
+    Deg,
+    Rad,
+}
+
+impl Function {
+    fn parse(name: &str) -> Option<(Function, usize)> {
+        Some(match name {
+            "sqrt" => (Function::Sqrt, 1),
+            "abs" => (Function::Abs, 1),
+            "sin" => (Function::Sin, 1),
+            "cos" => (Function::Cos, 1),
+            "tan" => (Function::Tan, 1),
+            "asin" => (Function::Asin, 1),
+            "acos" => (Function::Acos, 1),
+            "atan" => (Function::Atan, 1),
+            "atan2" => (Function::Atan2, 2),
+            "exp" => (Function::Exp, 1),
+            "ln" => (Function::Ln, 1),
+            "log10" => (Function::Log10, 1),
+            "pow" => (Function::Pow, 2),
+            "min" => (Function::Min, 2),
+            "max" => (Function::Max, 2),
+            "hypot" => (Function::Hypot, 2),
+            "deg" => (Function::Deg, 1),
+            "rad" => (Function::Rad, 1),
+            _ => return None,
+        })
+    }
+
+    fn apply(&self, a: f64, b: f64) -> f64 {
+        match self {
+            Function::Sqrt => a.sqrt(),
+            Function::Abs => a.abs(),
+            Function::Sin => a.sin(),
+            Function::Cos => a.cos(),
+            Function::Tan => a.tan(),
+            Function::Asin => a.asin(),
+            Function::Acos => a.acos(),
+            Function::Atan => a.atan(),
+            Function::Atan2 => a.atan2(b),
+            Function::Exp => a.exp(),
+            Function::Ln => a.ln(),
+            Function::Log10 => a.log10(),
+            Function::Pow => a.powf(b),
+            Function::Min => a.min(b),
+            Function::Max => a.max(b),
+            Function::Hypot => a.hypot(b),
+            Function::Deg => a.to_degrees(),
+            Function::Rad => a.to_radians(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f64),
+    /// index into the channel values
+    Channel(usize),
+    /// seconds since the first sample
+    Time,
+    /// seconds since the previous sample
+    Dt,
+    Neg(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+    /// value of the inner expression at the previous sample
+    Prev(Box<Expr>, Option<f64>),
+}
+
+impl Expr {
+    fn eval(&mut self, ctx: &Context) -> f64 {
+        match self {
+            Expr::Number(v) => *v,
+            Expr::Channel(i) => ctx.values[*i],
+            Expr::Time => ctx.t,
+            Expr::Dt => ctx.dt,
+            Expr::Neg(e) => -e.eval(ctx),
+            Expr::Binary(op, l, r) => {
+                let (l, r) = (l.eval(ctx), r.eval(ctx));
+                match op {
+                    '+' => l + r,
+                    '-' => l - r,
+                    '*' => l * r,
+                    '/' => l / r,
+                    '%' => l % r,
+                    _ => l.powf(r),
+                }
+            }
+            Expr::Call(f, args) => {
+                let a = args[0].eval(ctx);
+                let b = args.get_mut(1).map_or(0., |b| b.eval(ctx));
+                f.apply(a, b)
+            }
+            Expr::Prev(e, last) => {
+                let current = e.eval(ctx);
+                last.replace(current).unwrap_or(current)
+            }
+        }
+    }
+}
+
+/// Values a [`Formula`] is evaluated against
+pub struct Context<'a> {
+    /// channel values of the current sample, ordered as the channels
+    /// the formula was parsed with
+    pub values: &'a [f64],
+    pub t: f64,
+    pub dt: f64,
+}
+
+/// Parsed user formula such as `sqrt(ax^2+ay^2+az^2)` or `deg(atan2(my, mx))`.
+///
+/// Supports `+ - * / % ^`, parentheses, math functions, channel names
+/// (with `ax`..`mz` as short names for the raw axes), `t`, `dt`, `pi`
+/// and `prev(expr)`, which is the value of `expr` at the previous sample.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Formula {
+    expr: Expr,
+}
+
+impl Formula {
+    /// Fails with the position and cause of the first problem
+    pub fn parse(source: &str, channels: &[ChannelInfo]) -> Result<Self, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            channels,
+            source_len: source.len(),
+        };
+        let expr = parser.expression()?;
+        if let Some((pos, token)) = parser.tokens.get(parser.pos) {
+            return Err(format!("unexpected {token:?} at {pos}"));
+        }
+        Ok(Self { expr })
+    }
+
+    pub fn eval(&mut self, ctx: &Context) -> f64 {
+        self.expr.eval(ctx)
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, String> {
+    let mut tokens = vec![];
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(pos, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = pos;
+            while let Some(&(i, c)) = chars.peek() {
+                let exponent_sign =
+                    (c == '-' || c == '+') && source[..i].ends_with(['e', 'E']) && i > pos;
+                if !(c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || exponent_sign) {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            let number = source[pos..end]
+                .parse()
+                .map_err(|_| format!("bad number {} at {pos}", &source[pos..end]))?;
+            tokens.push((pos, Token::Number(number)));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = pos;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push((pos, Token::Ident(String::from(&source[pos..end]))));
+        } else {
+            let token = match c {
+                '+' | '-' | '*' | '/' | '%' | '^' => Token::Op(c),
+                '(' => Token::Open,
+                ')' => Token::Close,
+                ',' => Token::Comma,
+                _ => return Err(format!("unexpected '{c}' at {pos}")),
+            };
+            tokens.push((pos, token));
+            chars.next();
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    channels: &'a [ChannelInfo],
+    source_len: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|t| &t.1)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.source_len, |t| t.0)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .map(|t| t.1.clone())
+            .ok_or_else(|| String::from("unexpected end of formula"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        let pos = self.position();
+        match self.next()? {
+            t if t == expected => Ok(()),
+            t => Err(format!("expected {expected:?}, found {t:?} at {pos}")),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expr, String> {
+        let mut left = self.term()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.term()?));
+        }
+        Ok(left)
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut left = self.unary()?;
+        while let Some(Token::Op(op @ ('*' | '/' | '%'))) = self.peek().cloned() {
+            self.pos += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.peek() == Some(&Token::Op('-')) {
+            self.pos += 1;
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.primary()?;
+        if self.peek() == Some(&Token::Op('^')) {
+            self.pos += 1;
+            return Ok(Expr::Binary('^', Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let pos = self.position();
+        match self.next()? {
+            Token::Number(v) => Ok(Expr::Number(v)),
+            Token::Open => {
+                let e = self.expression()?;
+                self.expect(Token::Close)?;
+                Ok(e)
+            }
+            Token::Ident(name) if self.peek() == Some(&Token::Open) => {
+                self.pos += 1;
+                let mut args = vec![self.expression()?];
+                while self.peek() == Some(&Token::Comma) {
+                    self.pos += 1;
+                    args.push(self.expression()?);
+                }
+                self.expect(Token::Close)?;
+                if name == "prev" {
+                    if args.len() != 1 {
+                        return Err(format!("prev takes 1 argument at {pos}"));
+                    }
+                    return Ok(Expr::Prev(Box::new(args.remove(0)), None));
+                }
+                let (f, arity) =
+                    Function::parse(&name).ok_or(format!("unknown function {name} at {pos}"))?;
+                if args.len() != arity {
+                    return Err(format!("{name} takes {arity} argument(s) at {pos}"));
+                }
+                Ok(Expr::Call(f, args))
+            }
+            Token::Ident(name) => {
+                let channel = ALIASES
+                    .iter()
+                    .find(|(alias, _)| *alias == name)
+                    .map_or(name.as_str(), |(_, channel)| channel);
+                if let Some(i) = self.channels.iter().position(|c| c.name == channel) {
+                    return Ok(Expr::Channel(i));
+                }
+                match name.as_str() {
+                    "t" => Ok(Expr::Time),
+                    "dt" => Ok(Expr::Dt),
+                    "pi" => Ok(Expr::Number(std::f64::consts::PI)),
+                    _ => Err(format!("unknown channel {name} at {pos}")),
+                }
+            }
+            t => Err(format!("unexpected {t:?} at {pos}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channels() -> Vec<ChannelInfo> {
+        ["acc_x", "acc_y", "acc_z", "mag_x", "mag_y"]
+            .into_iter()
+            .map(|n| ChannelInfo::new(n, ""))
+            .collect()
+    }
+
+    fn eval(source: &str, values: &[f64]) -> f64 {
+        let mut f = Formula::parse(source, &channels()).unwrap();
+        f.eval(&Context {
+            values,
+            t: 1.,
+            dt: 0.1,
+        })
+    }
+
+    #[test]
+    fn test_eval() {
+        let v = [3., 4., 12., 0., 1.];
+        assert_eq!(eval("sqrt(ax^2+ay^2+acc_z^2)", &v), 13.);
+        assert_eq!(eval("-2^2 + 10 % 4 * 3", &v), 2.);
+        assert_eq!(eval("deg(atan2(my, mx))", &v), 90.);
+        assert_eq!(eval("2^-1 / dt", &v), 5.);
+        assert_eq!(eval("1.5e-1 * 2e1", &v), 3.);
+    }
+
+    #[test]
+    fn test_prev() {
+        let mut f = Formula::parse("(ax - prev(ax)) / dt", &channels()).unwrap();
+        let mut out = vec![];
+        for (i, x) in [1., 2., 4.].into_iter().enumerate() {
+            out.push(f.eval(&Context {
+                values: &[x, 0., 0., 0., 0.],
+                t: i as f64 * 0.5,
+                dt: 0.5,
+            }));
+        }
+        assert_eq!(out, [0., 2., 4.]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let c = channels();
+        assert_eq!(
+            Formula::parse("ax + nope", &c).unwrap_err(),
+            "unknown channel nope at 5"
+        );
+        assert!(Formula::parse("atan2(ax)", &c).is_err());
+        assert!(Formula::parse("(ax", &c).is_err());
+        assert!(Formula::parse("ax ay", &c).is_err());
+        assert!(Formula::parse("ax $ 2", &c).is_err());
+    }
+}
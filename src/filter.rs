@@ -0,0 +1,385 @@
+use std::{collections::VecDeque, f64::consts::PI, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+
+/// Single channel filter fed with samples in timestamp order.
+///
+/// Timestamps are device milliseconds, filters derive their coefficients
+/// from the real spacing between samples instead of a nominal sample rate.
+pub trait Filter {
+    fn apply(&mut self, timestamp: u64, value: f64) -> f64;
+}
+
+/// Filter description, used to build fresh [`Filter`] instances
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum FilterKind {
+    MovingAverage {
+        window_ms: u64,
+    },
+    Median {
+        window: usize,
+    },
+    /// first order IIR
+    LowPass {
+        cutoff_hz: f64,
+    },
+    /// first order IIR
+    HighPass {
+        cutoff_hz: f64,
+    },
+    ButterworthLowPass {
+        cutoff_hz: f64,
+    },
+    ButterworthHighPass {
+        cutoff_hz: f64,
+    },
+}
+
+// cutoffs are never NaN, needed for the filter pick list
+impl Eq for FilterKind {}
+
+impl FilterKind {
+    pub const PRESETS: [FilterKind; 6] = [
+        FilterKind::MovingAverage { window_ms: 500 },
+        FilterKind::Median { window: 5 },
+        FilterKind::LowPass { cutoff_hz: 1.0 },
+        FilterKind::HighPass { cutoff_hz: 0.5 },
+        FilterKind::ButterworthLowPass { cutoff_hz: 1.0 },
+        FilterKind::ButterworthHighPass { cutoff_hz: 0.5 },
+    ];
+
+    pub fn build(&self) -> Box<dyn Filter + Send> {
+        match *self {
+            FilterKind::MovingAverage { window_ms } => Box::new(MovingAverage::new(window_ms)),
+            FilterKind::Median { window } => Box::new(Median::new(window)),
+            FilterKind::LowPass { cutoff_hz } => Box::new(LowPass::new(cutoff_hz)),
+            FilterKind::HighPass { cutoff_hz } => Box::new(HighPass::new(cutoff_hz)),
+            FilterKind::ButterworthLowPass { cutoff_hz } => {
+                Box::new(Biquad::new(BiquadKind::LowPass, cutoff_hz))
+            }
+            FilterKind::ButterworthHighPass { cutoff_hz } => {
+                Box::new(Biquad::new(BiquadKind::HighPass, cutoff_hz))
+            }
+        }
+    }
+}
+
+impl Display for FilterKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FilterKind::MovingAverage { window_ms } => write!(f, "moving average {window_ms}ms"),
+            FilterKind::Median { window } => write!(f, "median of {window}"),
+            FilterKind::LowPass { cutoff_hz } => write!(f, "low-pass {cutoff_hz}Hz"),
+            FilterKind::HighPass { cutoff_hz } => write!(f, "high-pass {cutoff_hz}Hz"),
+            FilterKind::ButterworthLowPass { cutoff_hz } => {
+                write!(f, "butterworth low-pass {cutoff_hz}Hz")
+            }
+            FilterKind::ButterworthHighPass { cutoff_hz } => {
+                write!(f, "butterworth high-pass {cutoff_hz}Hz")
+            }
+        }
+    }
+}
+
+/// Filters applied one after another
+pub struct FilterChain {
+    kinds: Vec<FilterKind>,
+    filters: Vec<Box<dyn Filter + Send>>,
+}
+
+impl FilterChain {
+    pub fn new(kinds: &[FilterKind]) -> Self {
+        Self {
+            kinds: kinds.to_vec(),
+            filters: kinds.iter().map(FilterKind::build).collect(),
+        }
+    }
+}
+
+impl Filter for FilterChain {
+    fn apply(&mut self, timestamp: u64, value: f64) -> f64 {
+        self.filters
+            .iter_mut()
+            .fold(value, |v, f| f.apply(timestamp, v))
+    }
+}
+
+impl Clone for FilterChain {
+    /// Clones the configuration, the filter state starts fresh
+    fn clone(&self) -> Self {
+        Self::new(&self.kinds)
+    }
+}
+
+fn dt_seconds(prev: Option<u64>, timestamp: u64) -> Option<f64> {
+    prev.map(|p| timestamp.saturating_sub(p) as f64 / 1000.)
+        .filter(|dt| *dt > 0.)
+}
+
+/// Mean over the samples of the last `window_ms` milliseconds
+pub struct MovingAverage {
+    window_ms: u64,
+    values: VecDeque<(u64, f64)>,
+    sum: f64,
+}
+
+impl MovingAverage {
+    pub fn new(window_ms: u64) -> Self {
+        Self {
+            window_ms,
+            values: VecDeque::new(),
+            sum: 0.,
+        }
+    }
+}
+
+impl Filter for MovingAverage {
+    fn apply(&mut self, timestamp: u64, value: f64) -> f64 {
+        self.values.push_back((timestamp, value));
+        self.sum += value;
+        while let Some(&(t, v)) = self.values.front() {
+            if t + self.window_ms > timestamp || self.values.len() == 1 {
+                break;
+            }
+            self.sum -= v;
+            self.values.pop_front();
+        }
+        self.sum / self.values.len() as f64
+    }
+}
+
+/// Median of the last `window` samples
+pub struct Median {
+    window: usize,
+    values: VecDeque<f64>,
+}
+
+impl Median {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            values: VecDeque::new(),
+        }
+    }
+}
+
+impl Filter for Median {
+    fn apply(&mut self, _timestamp: u64, value: f64) -> f64 {
+        if self.values.len() == self.window {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+        let mut sorted: Vec<f64> = self.values.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let mid = sorted.len() / 2;
+        if sorted.len().is_multiple_of(2) {
+            (sorted[mid - 1] + sorted[mid]) / 2.
+        } else {
+            sorted[mid]
+        }
+    }
+}
+
+pub struct LowPass {
+    rc: f64,
+    prev: Option<(u64, f64)>,
+}
+
+impl LowPass {
+    pub fn new(cutoff_hz: f64) -> Self {
+        Self {
+            rc: 1. / (2. * PI * cutoff_hz),
+            prev: None,
+        }
+    }
+}
+
+impl Filter for LowPass {
+    fn apply(&mut self, timestamp: u64, value: f64) -> f64 {
+        let y = match self.prev {
+            Some((t, y)) => match dt_seconds(Some(t), timestamp) {
+                Some(dt) => y + dt / (self.rc + dt) * (value - y),
+                None => y,
+            },
+            None => value,
+        };
+        self.prev = Some((timestamp, y));
+        y
+    }
+}
+
+pub struct HighPass {
+    rc: f64,
+    // timestamp, input, output
+    prev: Option<(u64, f64, f64)>,
+}
+
+impl HighPass {
+    pub fn new(cutoff_hz: f64) -> Self {
+        Self {
+            rc: 1. / (2. * PI * cutoff_hz),
+            prev: None,
+        }
+    }
+}
+
+impl Filter for HighPass {
+    fn apply(&mut self, timestamp: u64, value: f64) -> f64 {
+        let y = match self.prev {
+            Some((t, x, y)) => match dt_seconds(Some(t), timestamp) {
+                Some(dt) => self.rc / (self.rc + dt) * (y + value - x),
+                None => y,
+            },
+            None => 0.,
+        };
+        self.prev = Some((timestamp, value, y));
+        y
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BiquadKind {
+    LowPass,
+    HighPass,
+}
+
+/// Second order Butterworth section, coefficients are recomputed
+/// from every sample interval
+pub struct Biquad {
+    kind: BiquadKind,
+    cutoff_hz: f64,
+    prev_timestamp: Option<u64>,
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    pub fn new(kind: BiquadKind, cutoff_hz: f64) -> Self {
+        Self {
+            kind,
+            cutoff_hz,
+            prev_timestamp: None,
+            x: [0.; 2],
+            y: [0.; 2],
+        }
+    }
+
+    /// normalized (b0, b1, b2, a1, a2)
+    fn coefficients(&self, dt: f64) -> (f64, f64, f64, f64, f64) {
+        let fs = 1. / dt;
+        // keep just below nyquist when the samples are too sparse
+        let w0 = (2. * PI * self.cutoff_hz / fs).min(PI * 0.99);
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / 2_f64.sqrt();
+        let a0 = 1. + alpha;
+        let (b0, b1) = match self.kind {
+            BiquadKind::LowPass => ((1. - cos) / 2., 1. - cos),
+            BiquadKind::HighPass => ((1. + cos) / 2., -(1. + cos)),
+        };
+        (b0 / a0, b1 / a0, b0 / a0, -2. * cos / a0, (1. - alpha) / a0)
+    }
+}
+
+impl Filter for Biquad {
+    fn apply(&mut self, timestamp: u64, value: f64) -> f64 {
+        let Some(dt) = dt_seconds(self.prev_timestamp, timestamp) else {
+            if self.prev_timestamp.is_none() {
+                // start from steady state to avoid a step response
+                let y = match self.kind {
+                    BiquadKind::LowPass => value,
+                    BiquadKind::HighPass => 0.,
+                };
+                self.prev_timestamp = Some(timestamp);
+                self.x = [value; 2];
+                self.y = [y; 2];
+            }
+            return self.y[0];
+        };
+        let (b0, b1, b2, a1, a2) = self.coefficients(dt);
+        let y = b0 * value + b1 * self.x[0] + b2 * self.x[1] - a1 * self.y[0] - a2 * self.y[1];
+        self.x = [value, self.x[0]];
+        self.y = [y, self.y[0]];
+        self.prev_timestamp = Some(timestamp);
+        y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(kind: FilterKind, input: impl Iterator<Item = (u64, f64)>) -> Vec<f64> {
+        let mut f = kind.build();
+        input.map(|(t, v)| f.apply(t, v)).collect()
+    }
+
+    #[test]
+    fn test_moving_average() {
+        let out = run(
+            FilterKind::MovingAverage { window_ms: 300 },
+            [(0, 1.), (100, 2.), (200, 3.), (300, 4.), (400, 5.)].into_iter(),
+        );
+        assert_eq!(out, vec![1., 1.5, 2., 3., 4.]);
+    }
+
+    #[test]
+    fn test_median_rejects_spike() {
+        let out = run(
+            FilterKind::Median { window: 3 },
+            [(0, 1.), (100, 1.), (200, 50.), (300, 1.)].into_iter(),
+        );
+        assert_eq!(out[2], 1.);
+        assert_eq!(out[3], 1.);
+    }
+
+    #[test]
+    fn test_low_pass_settles_on_dc() {
+        for kind in [
+            FilterKind::LowPass { cutoff_hz: 1. },
+            FilterKind::ButterworthLowPass { cutoff_hz: 1. },
+        ] {
+            let out = run(kind, (0..200).map(|i| (i * 107, 2.)));
+            assert!((out.last().unwrap() - 2.).abs() < 1e-6, "{kind}");
+        }
+    }
+
+    #[test]
+    fn test_low_pass_removes_vibration() {
+        use crate::generator::{Axis, Generator, GeneratorConfig, Tone};
+
+        let board = GeneratorConfig {
+            rate_hz: 200.,
+            tones: vec![Tone {
+                axis: Axis::X,
+                frequency_hz: 47.,
+                amplitude_g: 0.2,
+            }],
+            rotation_dps: [0.; 3],
+            noise_g: 0.,
+            dropout_probability: 0.,
+            jitter_ms: 0.,
+            ..Default::default()
+        };
+        let out = run(
+            FilterKind::ButterworthLowPass { cutoff_hz: 2. },
+            Generator::new(board)
+                .take(2000)
+                .map(|d| (d.timestamp, d.acc.x)),
+        );
+        let worst = out[1000..].iter().fold(0f64, |m, v| m.max(v.abs()));
+        assert!(worst < 0.01, "{worst}");
+    }
+
+    #[test]
+    fn test_high_pass_removes_dc() {
+        for kind in [
+            FilterKind::HighPass { cutoff_hz: 1. },
+            FilterKind::ButterworthHighPass { cutoff_hz: 1. },
+        ] {
+            // jittered sample spacing like the test recordings
+            let out = run(kind, (0..200).map(|i| (i * 107 + i % 2, 2.)));
+            assert!(out.last().unwrap().abs() < 1e-6, "{kind}");
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use std::f64::consts::TAU;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, Stream};
+
+use crate::datasource::{AccData, Data, MagData, QualityEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+/// longest dropout, a day
+const MAX_DROPOUT_MS: u64 = 86_400_000;
+
+/// Sine on one accelerometer axis, slow motion and engine vibration alike
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tone {
+    pub axis: Axis,
+    pub frequency_hz: f64,
+    pub amplitude_g: f64,
+}
+
+/// Synthetic sensor board, in g and gauss. The same seed gives the same
+/// samples.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GeneratorConfig {
+    pub seed: u64,
+    pub rate_hz: f64,
+    /// device uptime at the first sample
+    pub start_ms: u64,
+    pub tones: Vec<Tone>,
+    /// constant turn rate about the x, y, z axes, turning gravity and the
+    /// magnetic field with it
+    pub rotation_dps: [f64; 3],
+    /// standard deviation of the accelerometer noise
+    pub noise_g: f64,
+    /// earth field at rest
+    pub field_gauss: [f64; 3],
+    /// offset of the board itself, turning with it
+    pub hard_iron_gauss: [f64; 3],
+    /// standard deviation of the magnetometer noise
+    pub noise_gauss: f64,
+    /// chance of a dropout starting at each sample
+    pub dropout_probability: f64,
+    pub dropout_ms: u64,
+    /// timestamps are off by up to this much either way
+    pub jitter_ms: f64,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            rate_hz: 100.,
+            start_ms: 0,
+            tones: vec![
+                Tone {
+                    axis: Axis::Z,
+                    frequency_hz: 0.5,
+                    amplitude_g: 0.05,
+                },
+                Tone {
+                    axis: Axis::X,
+                    frequency_hz: 47.,
+                    amplitude_g: 0.02,
+                },
+            ],
+            rotation_dps: [0., 0., 10.],
+            noise_g: 0.005,
+            field_gauss: [0.2, 0., -0.4],
+            hard_iron_gauss: [0.05, -0.03, 0.02],
+            noise_gauss: 0.002,
+            dropout_probability: 0.001,
+            dropout_ms: 800,
+            jitter_ms: 2.,
+        }
+    }
+}
+
+impl GeneratorConfig {
+    /// Errors start with the offending key
+    pub fn validate(&self) -> Result<(), String> {
+        if self.rate_hz <= 0. || !self.rate_hz.is_finite() {
+            return Err(String::from("rate_hz: has to be positive"));
+        }
+        if !(0. ..=1.).contains(&self.dropout_probability) {
+            return Err(String::from("dropout_probability: has to be 0 to 1"));
+        }
+        for (key, value) in [
+            ("noise_g", self.noise_g),
+            ("noise_gauss", self.noise_gauss),
+            ("jitter_ms", self.jitter_ms),
+        ] {
+            if value < 0. || !value.is_finite() {
+                return Err(format!("{key}: has to be 0 or more"));
+            }
+        }
+        if self.dropout_ms > MAX_DROPOUT_MS {
+            return Err(format!("dropout_ms: has to be at most {MAX_DROPOUT_MS}"));
+        }
+        for (i, tone) in self.tones.iter().enumerate() {
+            if tone.frequency_hz <= 0. || !tone.frequency_hz.is_finite() {
+                return Err(format!("tones[{i}].frequency_hz: has to be positive"));
+            }
+            if tone.amplitude_g < 0. || !tone.amplitude_g.is_finite() {
+                return Err(format!("tones[{i}].amplitude_g: has to be 0 or more"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// splitmix64, small and seedable
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// In 0..1
+    fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Standard normal, Box-Muller
+    fn gaussian(&mut self) -> f64 {
+        let u = self.uniform().max(f64::MIN_POSITIVE);
+        (-2. * u.ln()).sqrt() * (TAU * self.uniform()).cos()
+    }
+}
+
+/// `v` turned by the rotation vector `angle` in radians
+fn rotate(v: [f64; 3], angle: [f64; 3]) -> [f64; 3] {
+    let phi = angle.iter().map(|a| a * a).sum::<f64>().sqrt();
+    if phi == 0. {
+        return v;
+    }
+    let k = angle.map(|a| a / phi);
+    let cross = [
+        k[1] * v[2] - k[2] * v[1],
+        k[2] * v[0] - k[0] * v[2],
+        k[0] * v[1] - k[1] * v[0],
+    ];
+    let dot = k[0] * v[0] + k[1] * v[1] + k[2] * v[2];
+    let (sin, cos) = phi.sin_cos();
+    [0, 1, 2].map(|i| v[i] * cos + cross[i] * sin + k[i] * dot * (1. - cos))
+}
+
+/// Endless samples of a [`GeneratorConfig`]
+pub struct Generator {
+    config: GeneratorConfig,
+    rng: Rng,
+    /// samples generated so far, dropped ones included
+    index: u64,
+    /// sample index the current dropout ends at
+    dropout_until: u64,
+}
+
+impl Generator {
+    pub fn new(config: GeneratorConfig) -> Self {
+        Self {
+            rng: Rng(config.seed),
+            config,
+            index: 0,
+            dropout_until: 0,
+        }
+    }
+}
+
+impl Iterator for Generator {
+    type Item = Data;
+
+    fn next(&mut self) -> Option<Data> {
+        let c = &self.config;
+        let period_ms = 1000. / c.rate_hz;
+        loop {
+            let index = self.index;
+            self.index += 1;
+            if index < self.dropout_until {
+                continue;
+            }
+            if self.rng.uniform() < c.dropout_probability {
+                self.dropout_until =
+                    index.saturating_add((c.dropout_ms as f64 / period_ms).ceil() as u64);
+                continue;
+            }
+
+            let t = index as f64 * period_ms / 1000.;
+            // the world turned back is the world seen from the board
+            let angle = c.rotation_dps.map(|r| -(r * t).to_radians());
+            let mut acc = rotate([0., 0., -1.], angle);
+            for tone in &c.tones {
+                let axis = tone.axis as usize;
+                acc[axis] += tone.amplitude_g * (TAU * tone.frequency_hz * t).sin();
+            }
+            let field = rotate(c.field_gauss, angle);
+            let mut mag = [0, 1, 2].map(|i| field[i] + c.hard_iron_gauss[i]);
+            for v in &mut acc {
+                *v += c.noise_g * self.rng.gaussian();
+            }
+            for v in &mut mag {
+                *v += c.noise_gauss * self.rng.gaussian();
+            }
+            let jitter = c.jitter_ms * (2. * self.rng.uniform() - 1.);
+            let timestamp = c.start_ms as f64 + index as f64 * period_ms + jitter;
+
+            return Some(Data {
+                timestamp: timestamp.round().max(0.) as u64,
+                acc: AccData {
+                    x: acc[0],
+                    y: acc[1],
+                    z: acc[2],
+                },
+                mag: MagData {
+                    x: mag[0],
+                    y: mag[1],
+                    z: mag[2],
+                },
+            });
+        }
+    }
+}
+
+/// Samples of `config` in real time, like [`crate::datasource::stream_file`]
+pub fn stream(config: GeneratorConfig) -> impl Stream<Item = Result<Data, QualityEvent>> {
+    let (tx, rx) = mpsc::channel::<Result<Data, QualityEvent>>(10);
+    tokio::spawn(async move {
+        let start = tokio::time::Instant::now();
+        let start_ms = config.start_ms;
+        for data in Generator::new(config) {
+            let due = data.timestamp.saturating_sub(start_ms);
+            tokio::time::sleep_until(start + tokio::time::Duration::from_millis(due)).await;
+            // the receiver is dropped when another recording is opened
+            if tx.send(Ok(data)).await.is_err() {
+                return;
+            }
+        }
+    });
+    ReceiverStream::new(rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_board() {
+        let lines = |seed| {
+            Generator::new(GeneratorConfig {
+                seed,
+                ..Default::default()
+            })
+            .take(2000)
+            .map(|d| d.to_string())
+            .collect::<Vec<String>>()
+        };
+        assert_eq!(lines(7), lines(7));
+        assert_ne!(lines(7), lines(8));
+
+        let config = GeneratorConfig {
+            tones: vec![],
+            noise_g: 0.,
+            noise_gauss: 0.,
+            dropout_probability: 0.,
+            ..Default::default()
+        };
+        let samples: Vec<Data> = Generator::new(config).take(1000).collect();
+
+        for d in &samples {
+            // gravity keeps its magnitude while the board turns
+            let g = (d.acc.x.powi(2) + d.acc.y.powi(2) + d.acc.z.powi(2)).sqrt();
+            assert!((g - 1.).abs() < 1e-9);
+        }
+        // a quarter turn about z after 9s at 10deg/s, plus the hard iron
+        let d = &samples[900];
+        assert!((d.mag.x - 0.05).abs() < 1e-9 && (d.mag.y - -0.23).abs() < 1e-9);
+    }
+}
@@ -1,14 +1,51 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    ops::RangeInclusive,
+    time::{Duration, Instant},
+};
+
 use iced::{
-    widget::canvas::{path::lyon_path::geom::euclid::num::Ceil, Cache, Frame, Geometry},
-    Element, Length, Size,
+    advanced::{
+        layout, renderer,
+        text::{self, LineHeight, Shaping},
+        widget::Tree,
+        Clipboard, Layout, Renderer as _, Shell, Widget,
+    },
+    alignment,
+    event::Status,
+    keyboard,
+    mouse::{self, Cursor, ScrollDelta},
+    widget::{
+        canvas::{Cache, Event, Frame, Geometry},
+        checkbox, pick_list, row,
+    },
+    Alignment, Color, Element, Length, Point, Rectangle, Size,
 };
-use plotters::prelude::ChartBuilder;
+use plotters::{prelude::ChartBuilder, style::RGBColor};
 use plotters_iced::{plotters_backend::DrawingBackend, Chart, ChartWidget, Renderer};
 
 use super::Message;
+use crate::{
+    alarm::AlarmEvent,
+    clock::format_wall_clock,
+    derived::Row,
+    downsample::{lttb, min_max},
+    filter::{Filter, FilterChain, FilterKind},
+    history::History,
+    marker::Marker,
+    spectrum::{spectrum, Spectrum, Window},
+    style::{AppTheme, ChartStyle, LegendPosition, Palette},
+};
 
-const TIME_RANGE: u64 = 5000; // miliseconds
+/// gaps and alarms kept per chart
+const MAX_MARKERS: usize = 1000;
+/// values kept per filtered or integrated series
+const SERIES_CAPACITY: usize = 36_000;
+/// narrowest time range shown, in seconds
+pub const MIN_SPAN_S: f64 = 0.1;
 
+#[derive(Debug, Clone, Copy)]
 pub struct Datapoint {
     pub timestamp: u64,
     pub x: f64,
@@ -16,14 +53,436 @@ pub struct Datapoint {
     pub z: f64,
 }
 
-pub struct CurrentValue2DChart {
+impl Datapoint {
+    /// Values of up to three `channels` of the row, NaN for the missing ones
+    fn from_row(row: &Row, channels: &[usize]) -> Self {
+        let value = |i: usize| channels.get(i).map_or(f64::NAN, |&c| row.values[c]);
+        Self {
+            timestamp: row.timestamp,
+            x: value(0),
+            y: value(1),
+            z: value(2),
+        }
+    }
+}
+
+type AxisValue = fn(&Datapoint) -> f64;
+
+/// Where the values of a [`Series`] come from
+#[derive(Debug, Clone)]
+pub enum Source {
+    /// one channel of the rows
+    Channel(usize),
+    /// euclidean norm of several channels
+    Magnitude(Vec<usize>),
+}
+
+impl Source {
+    fn value(&self, row: &Row) -> f64 {
+        match self {
+            Source::Channel(c) => row.values[*c],
+            Source::Magnitude(channels) => channels
+                .iter()
+                .map(|c| row.values[*c] * row.values[*c])
+                .sum::<f64>()
+                .sqrt(),
+        }
+    }
+}
+
+/// What is done to the source values before they are drawn
+#[derive(Clone)]
+pub enum Transform {
+    Raw,
+    /// trapezoidal integral over time in seconds, times `scale`
+    Integrated {
+        scale: f64,
+        total: f64,
+        prev: Option<(u64, f64)>,
+    },
+    Filtered(FilterChain),
+}
+
+impl Transform {
+    pub fn integrated(scale: f64) -> Self {
+        Transform::Integrated {
+            scale,
+            total: 0.,
+            prev: None,
+        }
+    }
+
+    fn apply(&mut self, timestamp: u64, value: f64) -> f64 {
+        match self {
+            Transform::Raw => value,
+            Transform::Integrated { scale, total, prev } => {
+                if let Some((t, v)) = *prev {
+                    let dt = timestamp.saturating_sub(t) as f64 / 1000.;
+                    if (v + value).is_finite() {
+                        *total += (v + value) / 2. * dt * *scale;
+                    }
+                }
+                *prev = Some((timestamp, value));
+                *total
+            }
+            Transform::Filtered(chain) => chain.apply(timestamp, value),
+        }
+    }
+}
+
+/// Named line of a [`TimeSeriesChart`]
+#[derive(Clone)]
+pub struct Series {
+    pub label: String,
+    pub source: Source,
+    pub transform: Transform,
+    /// slot in the chart palette
+    pub color: usize,
+    /// index of the device whose rows are read, 0 for the main source
+    pub device: usize,
+    /// (timestamp, value) of stateful transforms, raw values are read from the rows
+    values: VecDeque<(u64, f64)>,
+}
+
+impl Series {
+    pub fn new(label: &str, source: Source, transform: Transform, color: usize) -> Self {
+        Self {
+            label: String::from(label),
+            source,
+            transform,
+            color,
+            device: 0,
+            values: VecDeque::new(),
+        }
+    }
+
+    pub fn on_device(mut self, device: usize) -> Self {
+        self.device = device;
+        self
+    }
+
+    pub fn raw(label: &str, channel: usize, color: usize) -> Self {
+        Self::new(label, Source::Channel(channel), Transform::Raw, color)
+    }
+
+    fn push(&mut self, row: &Row) {
+        if matches!(self.transform, Transform::Raw) {
+            return;
+        }
+        if self.values.len() == SERIES_CAPACITY {
+            self.values.pop_front();
+        }
+        let value = self.transform.apply(row.timestamp, self.source.value(row));
+        self.values.push_back((row.timestamp, value));
+    }
+
+    /// (timestamp, value) within `range`, `rows` holds the rows of every device
+    fn points(&self, rows: &[&History<Row>], range: RangeInclusive<u64>) -> Vec<(u64, f64)> {
+        match self.transform {
+            Transform::Raw => rows.get(self.device).map_or(vec![], |rows| {
+                rows.range(range)
+                    .map(|row| (row.timestamp, self.source.value(row)))
+                    .collect()
+            }),
+            _ => {
+                let start = self.values.partition_point(|v| v.0 < *range.start());
+                let end = self.values.partition_point(|v| v.0 <= *range.end());
+                self.values.range(start..end.max(start)).copied().collect()
+            }
+        }
+    }
+}
+
+/// Time series of any number of [`Series`] over the rows of one or more devices
+pub struct TimeSeriesChart {
     cache: Cache,
-    datapoints: Vec<Datapoint>,
     title: String,
+    series: Vec<Series>,
+    /// (start, end) timestamps without data, lines are not drawn across them
+    gaps: Vec<(u64, u64)>,
+    /// (start, end) of triggered alarms, `None` while still active
+    alarms: Vec<(u64, Option<u64>)>,
+    /// fixed value range, fitted to the visible values when `None`
+    y_range: Option<(f64, f64)>,
+    /// timestamp of the last row pushed
+    latest: u64,
+    style: ChartStyle,
+    theme: AppTheme,
 }
 
-impl Chart<Message> for CurrentValue2DChart {
-    type State = ();
+/// [`TimeSeriesChart`] drawn over the rows it reads from
+struct TimeSeriesView<'a> {
+    chart: &'a TimeSeriesChart,
+    /// rows of every device, indexed by [`Series::device`]
+    rows: Vec<&'a History<Row>>,
+    axis: &'a TimeAxis,
+    markers: &'a [Marker],
+}
+
+/// Pixel and value ranges of the plotting area of the last drawn chart
+#[derive(Debug, Clone)]
+struct PlotArea {
+    px: (i32, i32),
+    py: (i32, i32),
+    x: (f64, f64),
+    y: (f64, f64),
+    /// label and (left, top, right, bottom) of the legend entry of each series
+    legend: Vec<(String, (i32, i32, i32, i32))>,
+}
+
+impl PlotArea {
+    /// (seconds, value) under `point`, relative to the widget bounds
+    fn value_at(&self, point: Point) -> Option<(f64, f64)> {
+        let (px, py) = (point.x as f64, point.y as f64);
+        if px < self.px.0 as f64
+            || px > self.px.1 as f64
+            || py < self.py.0 as f64
+            || py > self.py.1 as f64
+        {
+            return None;
+        }
+        let fx = (px - self.px.0 as f64) / (self.px.1 - self.px.0).max(1) as f64;
+        let fy = (self.py.1 as f64 - py) / (self.py.1 - self.py.0).max(1) as f64;
+        Some((
+            self.x.0 + fx * (self.x.1 - self.x.0),
+            self.y.0 + fy * (self.y.1 - self.y.0),
+        ))
+    }
+
+    /// Position of `(seconds, value)`, relative to the widget bounds
+    fn position_of(&self, (t, v): (f64, f64)) -> Point {
+        let fx = (t - self.x.0) / (self.x.1 - self.x.0);
+        let fy = (v - self.y.0) / (self.y.1 - self.y.0);
+        Point::new(
+            self.px.0 as f32 + (fx * (self.px.1 - self.px.0) as f64) as f32,
+            self.py.1 as f32 - (fy * (self.py.1 - self.py.0) as f64) as f32,
+        )
+    }
+
+    /// Label of the series whose legend entry is under `point`
+    fn legend_at(&self, point: Point) -> Option<&str> {
+        let (x, y) = (point.x as i32, point.y as i32);
+        self.legend
+            .iter()
+            .find(|(_, (left, top, right, bottom))| {
+                x >= *left && x < *right && y >= *top && y < *bottom
+            })
+            .map(|(label, _)| label.as_str())
+    }
+
+    /// Value range per pixel on the (time, value) axes
+    fn scale(&self) -> (f64, f64) {
+        (
+            (self.x.1 - self.x.0) / (self.px.1 - self.px.0).max(1) as f64,
+            (self.y.1 - self.y.0) / (self.py.1 - self.py.0).max(1) as f64,
+        )
+    }
+}
+
+/// Time range and cursor shared by all time series charts, in seconds
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TimeAxis {
+    /// visible time span, each chart's own time range when `None`
+    pub span: Option<f64>,
+    /// end of the visible time range, following the latest sample when `None`
+    pub end: Option<f64>,
+    /// time under the mouse in any of the charts
+    pub cursor: Option<f64>,
+    /// wall-clock ms since the epoch at time 0, labels show absolute times
+    /// when known
+    pub origin: Option<u64>,
+}
+
+/// Hover and value axis state of a [`TimeSeriesChart`], the time axis is
+/// the shared [`TimeAxis`].
+///
+/// The wheel zooms the time axis, with shift the value axis. Dragging pans
+/// and stops following the latest samples, double click resets the view.
+/// Clicking a legend entry hides or shows its series.
+#[derive(Default)]
+struct ViewState {
+    /// labels of the series toggled off in the legend, they stay hidden
+    /// when other series come and go
+    hidden: Vec<String>,
+    /// zoomed or panned value range, the chart's own range when `None`
+    y_range: Option<(f64, f64)>,
+    /// cursor position and view (end, y range) when the drag started
+    drag: Option<(Point, f64, (f64, f64))>,
+    /// hovered position, relative to the widget bounds
+    cursor: Option<Point>,
+    last_click: Option<Instant>,
+    shift: bool,
+    area: RefCell<Option<PlotArea>>,
+    /// visible series of the last drawn chart, read by the [`CursorLayer`]
+    drawn: RefCell<Vec<DrawnSeries>>,
+}
+
+/// Visible points of a series as last drawn
+struct DrawnSeries {
+    label: String,
+    color: Color,
+    points: Vec<(u64, f64)>,
+}
+
+impl ViewState {
+    fn hidden(&self, series: &Series) -> bool {
+        self.hidden.contains(&series.label)
+    }
+}
+
+impl Chart<Message> for TimeSeriesView<'_> {
+    type State = ViewState;
+
+    fn update(
+        &self,
+        state: &mut Self::State,
+        event: Event,
+        bounds: Rectangle,
+        cursor: Cursor,
+    ) -> (Status, Option<Message>) {
+        let Some(area) = state.area.borrow().clone() else {
+            return (Status::Ignored, None);
+        };
+        let position = cursor.position_in(bounds);
+        let mut axis = *self.axis;
+        let status = match event {
+            Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                state.shift = modifiers.shift();
+                return (Status::Ignored, None);
+            }
+            Event::Mouse(mouse::Event::WheelScrolled { delta }) if position.is_some() => {
+                let lines = match delta {
+                    ScrollDelta::Lines { y, .. } => y as f64,
+                    ScrollDelta::Pixels { y, .. } => y as f64 / 50.,
+                };
+                let factor = 0.8f64.powf(lines);
+                let anchor = position.and_then(|p| area.value_at(p));
+                if state.shift {
+                    let (low, high) = state.y_range.unwrap_or(area.y);
+                    let center = anchor.map_or((low + high) / 2., |a| a.1);
+                    state.y_range = Some((
+                        center - (center - low) * factor,
+                        center + (high - center) * factor,
+                    ));
+                } else {
+                    let span = axis.span.unwrap_or(area.x.1 - area.x.0);
+                    axis.span = Some((span * factor).clamp(MIN_SPAN_S, 24. * 3600.));
+                    // a paused view zooms around the cursor, a following one keeps following
+                    if let (Some(end), Some((t, _))) = (axis.end, anchor) {
+                        axis.end = Some(t + (end - t) * factor);
+                    }
+                }
+                Status::Captured
+            }
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Right)) => {
+                let Some((t, _)) = position.and_then(|p| area.value_at(p)) else {
+                    return (Status::Ignored, None);
+                };
+                let timestamp = (t.max(0.) * 1000.) as u64;
+                return (Status::Captured, Some(Message::AddMarker(Some(timestamp))));
+            }
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => match position {
+                Some(p) if area.legend_at(p).is_some() => {
+                    let label = area.legend_at(p).unwrap_or_default();
+                    match state.hidden.iter().position(|l| l == label) {
+                        Some(i) => {
+                            state.hidden.remove(i);
+                        }
+                        None => state.hidden.push(String::from(label)),
+                    }
+                    Status::Captured
+                }
+                Some(p) if area.value_at(p).is_some() => {
+                    let now = Instant::now();
+                    if state
+                        .last_click
+                        .is_some_and(|t| now.duration_since(t) < Duration::from_millis(300))
+                    {
+                        *state = ViewState {
+                            area: RefCell::new(Some(area)),
+                            cursor: state.cursor,
+                            hidden: std::mem::take(&mut state.hidden),
+                            ..Default::default()
+                        };
+                        axis = TimeAxis {
+                            cursor: axis.cursor,
+                            origin: axis.origin,
+                            ..Default::default()
+                        };
+                    } else {
+                        state.last_click = Some(now);
+                        state.drag = Some((p, area.x.1, state.y_range.unwrap_or(area.y)));
+                    }
+                    Status::Captured
+                }
+                _ => return (Status::Ignored, None),
+            },
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+                if state.drag.is_some() =>
+            {
+                state.drag = None;
+                Status::Captured
+            }
+            Event::Mouse(mouse::Event::CursorMoved { .. }) => {
+                // only the chart the cursor leaves clears the shared cursor
+                if position.is_some() || state.cursor.is_some() {
+                    axis.cursor = position.and_then(|p| area.value_at(p)).map(|v| v.0);
+                }
+                state.cursor = position;
+                if let (Some((start, end, (low, high))), Some(p)) = (state.drag, position) {
+                    let (sx, sy) = area.scale();
+                    let dx = (p.x - start.x) as f64 * sx;
+                    let dy = (p.y - start.y) as f64 * sy;
+                    axis.end = Some(end - dx);
+                    state.y_range = Some((low + dy, high + dy));
+                    self.chart.cache.clear();
+                }
+                // hovering only moves the cursor layer, the series stay cached
+                let message = (axis != *self.axis).then_some(Message::AxisChanged(axis));
+                return (Status::Ignored, message);
+            }
+            Event::Mouse(mouse::Event::CursorLeft) if state.cursor.is_some() => {
+                state.cursor = None;
+                axis.cursor = None;
+                let message = (axis != *self.axis).then_some(Message::AxisChanged(axis));
+                return (Status::Ignored, message);
+            }
+            _ => return (Status::Ignored, None),
+        };
+        self.chart.cache.clear();
+        let message = (axis != *self.axis).then_some(Message::AxisChanged(axis));
+        (status, message)
+    }
+
+    fn mouse_interaction(
+        &self,
+        state: &Self::State,
+        bounds: Rectangle,
+        cursor: Cursor,
+    ) -> mouse::Interaction {
+        match (state.drag, cursor.position_in(bounds)) {
+            (Some(_), _) => mouse::Interaction::Grabbing,
+            (None, Some(p))
+                if state
+                    .area
+                    .borrow()
+                    .as_ref()
+                    .is_some_and(|a| a.legend_at(p).is_some()) =>
+            {
+                mouse::Interaction::Pointer
+            }
+            (None, Some(p))
+                if state
+                    .area
+                    .borrow()
+                    .as_ref()
+                    .is_some_and(|a| a.value_at(p).is_some()) =>
+            {
+                mouse::Interaction::Crosshair
+            }
+            _ => mouse::Interaction::Idle,
+        }
+    }
 
     #[inline]
     fn draw<R: Renderer, F: Fn(&mut Frame)>(
@@ -32,117 +491,669 @@ impl Chart<Message> for CurrentValue2DChart {
         bounds: Size,
         draw_fn: F,
     ) -> Geometry {
-        renderer.draw_cache(&self.cache, bounds, draw_fn)
+        renderer.draw_cache(&self.chart.cache, bounds, draw_fn)
     }
 
-    fn build_chart<DB: DrawingBackend>(&self, _state: &Self::State, mut builder: ChartBuilder<DB>) {
+    fn build_chart<DB: DrawingBackend>(&self, state: &Self::State, mut builder: ChartBuilder<DB>) {
         use plotters::prelude::*;
 
-        let x_range_end = self
-            .datapoints
-            .last()
-            .and_then(|x| {
-                Some(if x.timestamp >= TIME_RANGE {
-                    x.timestamp as f64 / 1000.
-                } else {
-                    TIME_RANGE as f64 / 1000.
-                })
-            })
-            .unwrap_or(TIME_RANGE as f64 / 1000.);
-        let x_range_start = x_range_end - (TIME_RANGE as f64 / 1000.);
+        let chart_data = self.chart;
+        let latest = self
+            .rows
+            .iter()
+            .filter_map(|rows| rows.last())
+            .map(|r| r.timestamp)
+            .max()
+            .unwrap_or(0);
+        let (x_range_start, x_range_end) = chart_data.x_range(self.axis, latest);
+        let visible =
+            (x_range_start.max(0.) * 1000.) as u64..=(x_range_end.max(0.) * 1000.).ceil() as u64;
+        let magnitude = chart_data.magnitude_series();
+        let series: Vec<&Series> = chart_data.series.iter().chain(&magnitude).collect();
+        let points: Vec<Vec<(u64, f64)>> = series
+            .iter()
+            .map(|s| s.points(&self.rows, visible.clone()))
+            .collect();
+        let (y_min, y_max) = state.y_range.or(chart_data.y_range).unwrap_or_else(|| {
+            fitted_range(
+                series
+                    .iter()
+                    .zip(&points)
+                    .filter(|(s, _)| !state.hidden(s))
+                    .flat_map(|(_, p)| p.iter().map(|v| v.1)),
+            )
+        });
 
+        let colors = chart_data.theme.chart();
         let mut chart = builder
-            .caption(&self.title, ("sasns-serif", 30, &BLACK))
+            .caption(
+                &chart_data.title,
+                ("sans-serif", 30).into_font().color(&colors.text),
+            )
             .x_label_area_size(40)
             .y_label_area_size(40)
-            .build_cartesian_2d(x_range_start..x_range_end, -1.0..1.0)
+            .build_cartesian_2d(x_range_start..x_range_end, y_min..y_max)
             .unwrap();
 
-        chart.configure_mesh().draw().unwrap();
+        let origin = self.axis.origin;
+        let wall_clock =
+            |x: &f64| format_wall_clock(origin.unwrap_or(0) + (x.max(0.) * 1000.) as u64);
+        let mut mesh = chart.configure_mesh();
+        mesh.axis_style(colors.text)
+            .bold_line_style(colors.mesh.mix(0.2))
+            .light_line_style(colors.mesh.mix(0.05))
+            .label_style(("sans-serif", 12).into_font().color(&colors.text));
+        if origin.is_some() {
+            mesh.x_label_formatter(&wall_clock);
+        }
+        mesh.draw().unwrap();
+        let x_range = (x_range_start, x_range_end);
+        let width = chart.plotting_area().dim_in_pixel().0 as usize;
+        let (px, py) = chart.plotting_area().get_pixel_range();
+        let mut area = PlotArea {
+            px: (px.start, px.end),
+            py: (py.start, py.end),
+            x: x_range,
+            y: (y_min, y_max),
+            legend: vec![],
+        };
 
         chart
-            .draw_series(LineSeries::new(
-                self.datapoints
-                    .iter()
-                    .map(|x| (x.timestamp as f64 / 1000., x.x)),
-                &RED,
-            ))
-            .unwrap()
-            .label("X")
-            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &RED));
-        chart
-            .draw_series(LineSeries::new(
-                self.datapoints
-                    .iter()
-                    .map(|x| (x.timestamp as f64 / 1000., x.y)),
-                &GREEN,
-            ))
-            .unwrap()
-            .label("Y")
-            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &GREEN));
-        chart
-            .draw_series(LineSeries::new(
-                self.datapoints
-                    .iter()
-                    .map(|x| (x.timestamp as f64 / 1000., x.z)),
-                &BLUE,
-            ))
-            .unwrap()
-            .label("Z")
-            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &BLUE));
+            .draw_series(chart_data.gaps.iter().map(|(start, end)| {
+                Rectangle::new(
+                    [(*start as f64 / 1000., y_min), (*end as f64 / 1000., y_max)],
+                    colors.mesh.mix(0.1).filled(),
+                )
+            }))
+            .unwrap();
 
         chart
-            .configure_series_labels()
-            .border_style(&BLACK)
-            .background_style(&WHITE.mix(0.8))
-            .position(SeriesLabelPosition::MiddleLeft)
-            .draw()
+            .draw_series(chart_data.alarms.iter().map(|(start, end)| {
+                Rectangle::new(
+                    [
+                        (*start as f64 / 1000., y_min),
+                        (end.unwrap_or(chart_data.latest) as f64 / 1000., y_max),
+                    ],
+                    RED.mix(0.15).filled(),
+                )
+            }))
+            .unwrap();
+
+        let style = chart_data.style;
+        let palette = style.palette.colors(chart_data.theme);
+        let filtered = chart_data.is_filtered();
+        // raw lines fade behind filtered ones, integrated ones are estimates
+        let line_style = |s: &Series| {
+            let color = palette[s.color % palette.len()];
+            match s.transform {
+                Transform::Raw if filtered => color.mix(0.3).stroke_width(style.line_width),
+                Transform::Raw => color.mix(1.0).stroke_width(style.line_width),
+                Transform::Integrated { .. } => color.mix(0.6).stroke_width(style.line_width),
+                Transform::Filtered(_) => color.mix(1.0).stroke_width(style.line_width + 1),
+            }
+        };
+        for (s, points) in series.iter().zip(&points) {
+            if state.hidden(s) {
+                continue;
+            }
+            let line = line_style(s);
+            chart
+                .draw_series(chart_data.segments(points).map(|segment| {
+                    let segment: Vec<(f64, f64)> = segment
+                        .iter()
+                        .filter(|p| p.1.is_finite())
+                        .map(|(t, v)| (*t as f64 / 1000., *v))
+                        .collect();
+                    let points = match s.transform {
+                        // smooth series, a point per pixel keeps their shape
+                        Transform::Filtered(_) => {
+                            let threshold = match (segment.first(), segment.last()) {
+                                (Some(first), Some(last)) => {
+                                    ((last.0 - first.0) / (x_range.1 - x_range.0) * width as f64)
+                                        as usize
+                                }
+                                _ => 0,
+                            };
+                            lttb(&segment, threshold)
+                        }
+                        _ => min_max(&segment, x_range, width),
+                    };
+                    PathElement::new(points, line)
+                }))
+                .unwrap();
+        }
+
+        for m in self
+            .markers
+            .iter()
+            .filter(|m| visible.contains(&m.timestamp))
+        {
+            let t = m.timestamp as f64 / 1000.;
+            chart
+                .draw_series(std::iter::once(
+                    EmptyElement::at((t, y_max))
+                        + PathElement::new(
+                            vec![(0, 0), (0, py.end - py.start)],
+                            colors.text.mix(0.7).stroke_width(2),
+                        )
+                        + Text::new(
+                            m.label.clone(),
+                            (4, 22),
+                            ("sans-serif", 14).into_font().color(&colors.text),
+                        ),
+                ))
+                .unwrap();
+        }
+
+        // own legend instead of plotters' so entries can be clicked
+        const ENTRY_HEIGHT: i32 = 20;
+        let legend_width = 40 + 8 * series.iter().map(|s| s.label.len()).max().unwrap_or(0) as i32;
+        let size = (legend_width, ENTRY_HEIGHT * series.len() as i32 + 6);
+        let (lx, ly) = style
+            .legend
+            .place((px.end - px.start, py.end - py.start), size);
+        let plot = chart.plotting_area().strip_coord_spec();
+        plot.draw(&Rectangle::new(
+            [(lx, ly), (lx + size.0, ly + size.1)],
+            colors.background.mix(0.8).filled(),
+        ))
+        .unwrap();
+        plot.draw(&Rectangle::new(
+            [(lx, ly), (lx + size.0, ly + size.1)],
+            colors.text,
+        ))
+        .unwrap();
+        for (i, s) in series.iter().enumerate() {
+            let y = ly + 3 + i as i32 * ENTRY_HEIGHT;
+            let (line, text_color) = if state.hidden(s) {
+                (
+                    line_style(s).color.mix(0.2).stroke_width(2),
+                    colors.text.mix(0.3),
+                )
+            } else {
+                let line = line_style(s);
+                (
+                    line.stroke_width(line.stroke_width.max(2)),
+                    colors.text.mix(1.0),
+                )
+            };
+            let mid = y + ENTRY_HEIGHT / 2;
+            plot.draw(&PathElement::new(vec![(lx + 5, mid), (lx + 30, mid)], line))
+                .unwrap();
+            plot.draw(&Text::new(
+                s.label.clone(),
+                (lx + 35, y + 3),
+                ("sans-serif", 15).into_font().color(&text_color),
+            ))
             .unwrap();
+            area.legend.push((
+                s.label.clone(),
+                (
+                    px.start + lx,
+                    py.start + y,
+                    px.start + lx + size.0,
+                    py.start + y + ENTRY_HEIGHT,
+                ),
+            ));
+        }
+        *state.area.borrow_mut() = Some(area);
+        *state.drawn.borrow_mut() = series
+            .iter()
+            .zip(points)
+            .filter(|(s, _)| !state.hidden(s))
+            .map(|(s, points)| DrawnSeries {
+                label: s.label.clone(),
+                color: iced_color(palette[s.color % palette.len()]),
+                points,
+            })
+            .collect();
     }
 }
 
-impl CurrentValue2DChart {
-    pub fn view(&self) -> Element<Message> {
-        let chart = ChartWidget::new(self)
-            .height(Length::FillPortion(3))
-            .width(Length::FillPortion(3));
+fn iced_color(c: RGBColor) -> Color {
+    Color::from_rgb8(c.0, c.1, c.2)
+}
 
-        chart.into()
+/// Point of sorted `points` nearest to `t`
+fn nearest(points: &[(u64, f64)], t: u64) -> Option<(u64, f64)> {
+    let i = points.partition_point(|p| p.0 < t);
+    [i.checked_sub(1), Some(i)]
+        .into_iter()
+        .flatten()
+        .filter_map(|i| points.get(i))
+        .min_by_key(|p| p.0.abs_diff(t))
+        .copied()
+}
+
+/// Shared cursor drawn over a [`TimeSeriesView`] on a layer of its own, so
+/// moving it leaves the cached series alone: the time line in every chart,
+/// the value line only in the hovered one.
+struct CursorLayer<'a> {
+    chart: Element<'a, Message>,
+    axis: &'a TimeAxis,
+    text: Color,
+}
+
+impl Widget<Message, iced::Renderer> for CursorLayer<'_> {
+    fn width(&self) -> Length {
+        self.chart.as_widget().width()
     }
-    pub fn push_datapoint(&mut self, timestamp: u64, x: f64, y: f64, z: f64) {
-        self.datapoints
-            .retain(|x| x.timestamp > timestamp.saturating_sub(TIME_RANGE));
-        self.datapoints.push(Datapoint { timestamp, x, y, z });
-        self.cache.clear()
+
+    fn height(&self) -> Length {
+        self.chart.as_widget().height()
     }
 
-    pub fn with_title(title: &str) -> Self {
-        Self {
-            cache: Cache::new(),
-            datapoints: vec![],
-            title: String::from(title),
+    fn layout(&self, renderer: &iced::Renderer, limits: &layout::Limits) -> layout::Node {
+        self.chart.as_widget().layout(renderer, limits)
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.chart)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(std::slice::from_ref(&self.chart));
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: iced::Event,
+        layout: Layout<'_>,
+        cursor: Cursor,
+        renderer: &iced::Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> Status {
+        self.chart.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event,
+            layout,
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: Cursor,
+        viewport: &Rectangle,
+        renderer: &iced::Renderer,
+    ) -> mouse::Interaction {
+        self.chart.as_widget().mouse_interaction(
+            &tree.children[0],
+            layout,
+            cursor,
+            viewport,
+            renderer,
+        )
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut iced::Renderer,
+        theme: &iced::Theme,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: Cursor,
+        viewport: &Rectangle,
+    ) {
+        let child = &tree.children[0];
+        self.chart
+            .as_widget()
+            .draw(child, renderer, theme, style, layout, cursor, viewport);
+
+        let state = child.state.downcast_ref::<ViewState>();
+        let Some(t) = self.axis.cursor.filter(|_| state.drag.is_none()) else {
+            return;
+        };
+        let area = state.area.borrow();
+        let Some(area) = area.as_ref() else {
+            return;
+        };
+        let drawn = state.drawn.borrow();
+        let t_ms = (t.max(0.) * 1000.) as u64;
+        let values: Vec<(&DrawnSeries, (u64, f64))> = drawn
+            .iter()
+            .filter_map(|s| Some((s, nearest(&s.points, t_ms)?)))
+            .collect();
+        let Some(ts) = values
+            .iter()
+            .map(|(_, v)| v.0)
+            .min_by_key(|ts| ts.abs_diff(t_ms))
+        else {
+            return;
+        };
+
+        let bounds = layout.bounds();
+        let (left, right) = (area.px.0 as f32, area.px.1 as f32);
+        let (top, bottom) = (area.py.0 as f32, area.py.1 as f32);
+        let x = area.position_of((ts as f64 / 1000., area.y.0)).x;
+        if x < left || x > right {
+            return;
         }
+        let line = Color {
+            a: 0.5,
+            ..self.text
+        };
+        let quad = |x: f32, y: f32, width: f32, height: f32, radius: f32| renderer::Quad {
+            bounds: Rectangle {
+                x: bounds.x + x,
+                y: bounds.y + y,
+                width,
+                height,
+            },
+            border_radius: radius.into(),
+            border_width: 0.,
+            border_color: Color::TRANSPARENT,
+        };
+        let hovered = state
+            .cursor
+            .filter(|p| area.value_at(*p).is_some())
+            .map(|p| p.y);
+        let readout = values
+            .iter()
+            .fold(format!("{:.3}s", ts as f64 / 1000.), |text, (s, (_, v))| {
+                format!("{text}  {} {v:.4}", s.label)
+            });
+
+        renderer.with_layer(bounds, |renderer| {
+            renderer.fill_quad(quad(x, top, 1., bottom - top, 0.), line);
+            if let Some(y) = hovered {
+                renderer.fill_quad(quad(left, y, right - left, 1., 0.), line);
+            }
+            for (s, v) in &values {
+                if !v.1.is_finite() || v.1 < area.y.0 || v.1 > area.y.1 {
+                    continue;
+                }
+                let p = area.position_of((v.0 as f64 / 1000., v.1));
+                renderer.fill_quad(quad(p.x - 4., p.y - 4., 8., 8., 4.), s.color);
+            }
+            text::Renderer::fill_text(
+                renderer,
+                text::Text {
+                    content: &readout,
+                    bounds: Rectangle {
+                        x: bounds.x + left + 5.,
+                        y: bounds.y + top + 5.,
+                        width: right - left,
+                        height: 20.,
+                    },
+                    size: 15.,
+                    line_height: LineHeight::default(),
+                    color: self.text,
+                    font: text::Renderer::default_font(renderer),
+                    horizontal_alignment: alignment::Horizontal::Left,
+                    vertical_alignment: alignment::Vertical::Top,
+                    shaping: Shaping::Basic,
+                },
+            );
+        });
     }
 }
 
-impl Default for CurrentValue2DChart {
-    fn default() -> Self {
+impl TimeSeriesChart {
+    /// Chart of `series` with the value range fitted to the data
+    pub fn new(title: &str, series: Vec<Series>) -> Self {
         Self {
             cache: Cache::new(),
-            datapoints: vec![],
-            title: String::from("default current value chart"),
+            title: String::from(title),
+            series,
+            gaps: vec![],
+            alarms: vec![],
+            y_range: None,
+            latest: 0,
+            style: ChartStyle::default(),
+            theme: AppTheme::default(),
+        }
+    }
+
+    /// Chart of the x, y, z `channels` in a fixed -1..1 range
+    pub fn xyz(title: &str, channels: [usize; 3]) -> Self {
+        let series = ["X", "Y", "Z"]
+            .into_iter()
+            .zip(channels)
+            .enumerate()
+            .map(|(i, (label, channel))| Series::raw(label, channel, i))
+            .collect();
+        Self::new(title, series).with_y_range(-1., 1.)
+    }
+
+    /// Read every series from the rows of `device`
+    pub fn on_device(mut self, device: usize) -> Self {
+        for series in &mut self.series {
+            series.device = device;
+        }
+        self
+    }
+
+    pub fn with_y_range(mut self, low: f64, high: f64) -> Self {
+        self.y_range = Some((low, high));
+        self
+    }
+
+    pub fn view<'a>(
+        &'a self,
+        rows: Vec<&'a History<Row>>,
+        axis: &'a TimeAxis,
+        markers: &'a [Marker],
+    ) -> Element<'a, Message> {
+        let chart = ChartWidget::new(TimeSeriesView {
+            chart: self,
+            rows,
+            axis,
+            markers,
+        })
+        .height(Length::FillPortion(3))
+        .width(Length::FillPortion(3));
+
+        Element::new(CursorLayer {
+            chart: chart.into(),
+            axis,
+            text: iced_color(self.theme.chart().text),
+        })
+    }
+
+    /// Redraw on the next frame
+    pub fn redraw(&self) {
+        self.cache.clear()
+    }
+
+    /// Call after pushing `row` to the history of `device` the chart reads
+    /// from, the chart is not redrawn until [`Self::redraw`]
+    pub fn push_row(&mut self, device: usize, row: &Row) {
+        self.latest = self.latest.max(row.timestamp);
+        for series in self.series.iter_mut().filter(|s| s.device == device) {
+            series.push(row);
+        }
+    }
+
+    pub fn push_gap(&mut self, start: u64, end: u64) {
+        if self.gaps.len() == MAX_MARKERS {
+            self.gaps.remove(0);
+        }
+        self.gaps.push((start, end));
+    }
+
+    /// Highlight the alarm on the time axis, an ended alarm closes
+    /// the highlight started for it
+    pub fn push_alarm(&mut self, event: &AlarmEvent) {
+        if self.alarms.len() == MAX_MARKERS {
+            self.alarms.remove(0);
         }
+        match self
+            .alarms
+            .iter_mut()
+            .find(|(start, end)| *start == event.start && end.is_none())
+        {
+            Some(alarm) => alarm.1 = event.end,
+            None => self.alarms.push((event.start, event.end)),
+        }
+    }
+
+    /// Runs of points not interrupted by a gap
+    fn segments<'a>(&'a self, points: &'a [(u64, f64)]) -> impl Iterator<Item = &'a [(u64, f64)]> {
+        points.chunk_by(|a, b| {
+            !self
+                .gaps
+                .iter()
+                .any(|(start, end)| a.0 <= *start && b.0 >= *end)
+        })
+    }
+
+    fn is_filtered(&self) -> bool {
+        self.series
+            .iter()
+            .any(|s| matches!(s.transform, Transform::Filtered(_)))
+    }
+
+    /// Overlay each raw series with a filtered one, no overlay when `kinds` is
+    /// empty or the style leaves it out. The `rows` of the device of each
+    /// series are filtered again from scratch.
+    pub fn set_filter<'a>(
+        &mut self,
+        kinds: &[FilterKind],
+        rows: impl Fn(usize) -> Option<&'a History<Row>>,
+    ) {
+        self.series
+            .retain(|s| !matches!(s.transform, Transform::Filtered(_)));
+        if !kinds.is_empty() && self.style.filtered {
+            let filtered: Vec<Series> = self
+                .series
+                .iter()
+                .filter(|s| matches!(s.transform, Transform::Raw))
+                .map(|s| {
+                    let mut series = Series::new(
+                        &format!("{} filtered", s.label),
+                        s.source.clone(),
+                        Transform::Filtered(FilterChain::new(kinds)),
+                        s.color,
+                    )
+                    .on_device(s.device);
+                    for row in rows(s.device).iter().flat_map(|rows| rows.iter()) {
+                        series.push(row);
+                    }
+                    series
+                })
+                .collect();
+            self.series.extend(filtered);
+        }
+        self.cache.clear()
+    }
+
+    /// Magnitude of the raw channel series of the first device, when enabled
+    /// in the style
+    fn magnitude_series(&self) -> Option<Series> {
+        let device = self.series.first()?.device;
+        let channels: Vec<usize> = self
+            .series
+            .iter()
+            .filter(|s| s.device == device)
+            .filter_map(|s| match (&s.source, &s.transform) {
+                (Source::Channel(c), Transform::Raw) => Some(*c),
+                _ => None,
+            })
+            .collect();
+        (self.style.magnitude && !channels.is_empty()).then(|| {
+            Series::new("magnitude", Source::Magnitude(channels), Transform::Raw, 3)
+                .on_device(device)
+        })
+    }
+
+    pub fn style(&self) -> ChartStyle {
+        self.style
+    }
+
+    /// Visible (start, end) in seconds on `axis`, `latest` is the timestamp
+    /// of the last row
+    pub fn x_range(&self, axis: &TimeAxis, latest: u64) -> (f64, f64) {
+        let span = axis.span.unwrap_or(self.style.time_range_ms as f64 / 1000.);
+        let end = axis.end.unwrap_or((latest as f64 / 1000.).max(span));
+        (end - span, end)
+    }
+
+    pub fn set_style(&mut self, style: ChartStyle) {
+        self.style = style;
+        self.cache.clear()
+    }
+
+    pub fn set_theme(&mut self, theme: AppTheme) {
+        self.theme = theme;
+        self.cache.clear()
+    }
+
+    /// Magnitude, filter overlay, line width, palette and legend position
+    /// pickers
+    pub fn style_controls<'a>(
+        &self,
+        on_change: impl Fn(ChartStyle) -> Message + Copy + 'a,
+    ) -> Element<'a, Message> {
+        let style = self.style;
+        row![
+            checkbox("magnitude", style.magnitude, move |magnitude| {
+                on_change(ChartStyle { magnitude, ..style })
+            }),
+            checkbox("filtered", style.filtered, move |filtered| {
+                on_change(ChartStyle { filtered, ..style })
+            }),
+            pick_list(
+                &ChartStyle::LINE_WIDTHS[..],
+                Some(style.line_width),
+                move |line_width| on_change(ChartStyle {
+                    line_width,
+                    ..style
+                })
+            ),
+            pick_list(&Palette::ALL[..], Some(style.palette), move |palette| {
+                on_change(ChartStyle { palette, ..style })
+            }),
+            pick_list(
+                &LegendPosition::ALL[..],
+                Some(style.legend),
+                move |legend| { on_change(ChartStyle { legend, ..style }) }
+            ),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center)
+        .into()
     }
 }
 
-pub struct AggregateValue2DChart {
+/// Range of the finite `values` with a margin, -1..1 without any
+fn fitted_range(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    let (min, max) = values
+        .filter(|v| v.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), v| {
+            (min.min(v), max.max(v))
+        });
+    if min > max {
+        (-1., 1.)
+    } else {
+        let margin = ((max - min) * 0.1).max(1e-3);
+        (min - margin, max + margin)
+    }
+}
+
+/// Magnitude over frequency of the last `samples` rows of three channels
+pub struct SpectrumChart {
     cache: Cache,
     datapoints: Vec<Datapoint>,
     title: String,
-    y_range: (f64, f64),
+    channels: [usize; 3],
+    samples: usize,
+    window: Window,
+    spectra: [Spectrum; 3],
+    theme: AppTheme,
 }
 
-impl Chart<Message> for AggregateValue2DChart {
+impl Chart<Message> for SpectrumChart {
     type State = ();
 
     #[inline]
@@ -159,121 +1170,235 @@ impl Chart<Message> for AggregateValue2DChart {
         use plotters::prelude::*;
 
         let x_range_end = self
-            .datapoints
-            .last()
-            .and_then(|x| {
-                Some(if x.timestamp >= TIME_RANGE {
-                    x.timestamp as f64 / 1000.
-                } else {
-                    TIME_RANGE as f64 / 1000.
-                })
-            })
-            .unwrap_or(TIME_RANGE as f64 / 1000.);
-        let x_range_start = x_range_end - (TIME_RANGE as f64 / 1000.);
+            .spectra
+            .iter()
+            .filter_map(|s| s.frequencies.last())
+            .fold(1.0, |a: f64, b| a.max(*b));
+        let y_range_end = self
+            .spectra
+            .iter()
+            .flat_map(|s| s.magnitudes.iter())
+            .fold(0.01, |a: f64, b| a.max(*b))
+            * 1.2;
 
+        let colors = self.theme.chart();
         let mut chart = builder
-            .caption(&self.title, ("sasns-serif", 30, &BLACK))
+            .caption(
+                format!("{} ({} window)", self.title, self.window),
+                ("sans-serif", 30).into_font().color(&colors.text),
+            )
             .x_label_area_size(40)
-            .y_label_area_size(40)
-            .build_cartesian_2d(x_range_start..x_range_end, self.y_range.0..self.y_range.1)
+            .y_label_area_size(50)
+            .build_cartesian_2d(0.0..x_range_end, 0.0..y_range_end)
             .unwrap();
 
-        chart.configure_mesh().draw().unwrap();
-
         chart
-            .draw_series(LineSeries::new(
-                self.datapoints
-                    .iter()
-                    .map(|x| (x.timestamp as f64 / 1000., x.x)),
-                &RED,
-            ))
-            .unwrap()
-            .label("X")
-            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &RED));
-        chart
-            .draw_series(LineSeries::new(
-                self.datapoints
-                    .iter()
-                    .map(|x| (x.timestamp as f64 / 1000., x.y)),
-                &GREEN,
-            ))
-            .unwrap()
-            .label("Y")
-            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &GREEN));
-        chart
-            .draw_series(LineSeries::new(
-                self.datapoints
-                    .iter()
-                    .map(|x| (x.timestamp as f64 / 1000., x.z)),
-                &BLUE,
-            ))
-            .unwrap()
-            .label("Z")
-            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &BLUE));
+            .configure_mesh()
+            .x_desc("Hz")
+            .axis_style(colors.text)
+            .bold_line_style(colors.mesh.mix(0.2))
+            .light_line_style(colors.mesh.mix(0.05))
+            .label_style(("sans-serif", 12).into_font().color(&colors.text))
+            .axis_desc_style(("sans-serif", 12).into_font().color(&colors.text))
+            .draw()
+            .unwrap();
+
+        let palette = crate::style::Palette::Classic.colors(self.theme);
+        for ((label, color), spectrum) in
+            ["X", "Y", "Z"].into_iter().zip(palette).zip(&self.spectra)
+        {
+            chart
+                .draw_series(LineSeries::new(
+                    spectrum
+                        .frequencies
+                        .iter()
+                        .copied()
+                        .zip(spectrum.magnitudes.iter().copied()),
+                    color,
+                ))
+                .unwrap()
+                .label(label)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+            chart
+                .draw_series(spectrum.peaks(3).into_iter().map(|(f, m)| {
+                    EmptyElement::at((f, m))
+                        + Circle::new((0, 0), 3, color.filled())
+                        + Text::new(
+                            format!("{f:.2}Hz"),
+                            (5, -15),
+                            ("sans-serif", 15).into_font().color(&colors.text),
+                        )
+                }))
+                .unwrap();
+        }
 
         chart
             .configure_series_labels()
-            .border_style(&BLACK)
-            .background_style(&WHITE.mix(0.8))
-            .position(SeriesLabelPosition::MiddleLeft)
+            .border_style(colors.text)
+            .background_style(colors.background.mix(0.8))
+            .label_font(("sans-serif", 15).into_font().color(&colors.text))
+            .position(SeriesLabelPosition::UpperRight)
             .draw()
             .unwrap();
     }
 }
 
-impl AggregateValue2DChart {
-    pub fn view(&self) -> Element<Message> {
+impl SpectrumChart {
+    pub fn view(&self) -> Element<'_, Message> {
         let chart = ChartWidget::new(self)
             .height(Length::FillPortion(3))
             .width(Length::FillPortion(3));
 
         chart.into()
     }
-    pub fn push_datapoint(&mut self, timestamp: u64, x: f64, y: f64, z: f64) {
-        self.datapoints
-            .retain(|x| x.timestamp > timestamp.saturating_sub(TIME_RANGE));
-        let (x, y, z) = if !self.datapoints.is_empty() {
-            let prev = self.datapoints.last().unwrap();
-            let (x, y, z) = (prev.x + x, prev.y + y, prev.z + z);
-            (x, y, z)
-        } else {
-            (x, y, z)
-        };
-        self.datapoints.push(Datapoint { timestamp, x, y, z });
-        let new_max = [x, y, z]
-            .into_iter()
-            .max_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap();
-        let new_min = [x, y, z]
-            .into_iter()
-            .min_by(|a, b| a.partial_cmp(b).unwrap())
-            .unwrap();
-        if new_max > self.y_range.1 {
-            self.y_range.1 = new_max;
-        }
-        if new_min < self.y_range.0 {
-            self.y_range.0 = new_min;
+
+    /// Recompute the spectra from the last rows of `rows` and redraw
+    pub fn refresh(&mut self, rows: &History<Row>) {
+        self.datapoints = rows
+            .iter()
+            .rev()
+            .take(self.samples)
+            .rev()
+            .map(|row| Datapoint::from_row(row, &self.channels))
+            .collect();
+        self.update_spectra();
+    }
+
+    pub fn window(&self) -> Window {
+        self.window
+    }
+
+    pub fn set_window(&mut self, window: Window) {
+        self.window = window;
+        self.update_spectra();
+    }
+
+    pub fn set_theme(&mut self, theme: AppTheme) {
+        self.theme = theme;
+        self.cache.clear()
+    }
+
+    fn update_spectra(&mut self) {
+        let axes: [AxisValue; 3] = [|d| d.x, |d| d.y, |d| d.z];
+        for (s, value) in self.spectra.iter_mut().zip(axes) {
+            let samples: Vec<(u64, f64)> = self
+                .datapoints
+                .iter()
+                .map(|d| (d.timestamp, value(d)))
+                .collect();
+            *s = spectrum(&samples, self.window).unwrap_or_default();
         }
         self.cache.clear()
     }
 
-    pub fn with_title(title: &str) -> Self {
+    /// FFT over the last `samples` rows of the x, y, z `channels`
+    pub fn with_title(title: &str, channels: [usize; 3], samples: usize) -> Self {
         Self {
             cache: Cache::new(),
             datapoints: vec![],
             title: String::from(title),
-            y_range: (-1.0, 1.0),
+            channels,
+            samples,
+            window: Window::Hann,
+            spectra: Default::default(),
+            theme: AppTheme::default(),
         }
     }
 }
 
-impl Default for AggregateValue2DChart {
-    fn default() -> Self {
-        Self {
-            cache: Cache::new(),
-            datapoints: vec![],
-            title: String::from("default current value chart"),
-            y_range: (-1.0, 1.0),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integrated_series() {
+        let mut series = Series::new("v", Source::Channel(0), Transform::integrated(2.), 0);
+        for (timestamp, value) in [(0, 1.), (500, 1.), (1000, 3.)] {
+            series.push(&Row {
+                timestamp,
+                values: vec![value],
+            });
+        }
+        let rows = History::new(1);
+        assert_eq!(series.points(&[&rows], 400..=1000), [(500, 1.), (1000, 3.)]);
+    }
+
+    #[test]
+    fn test_series_read_their_device() {
+        let mut rows = [History::new(2), History::new(2)];
+        for (device, value) in [(0, 1.), (1, 2.)] {
+            rows[device].push(Row {
+                timestamp: 100,
+                values: vec![value],
+            });
         }
+        let rows: Vec<&History<Row>> = rows.iter().collect();
+        let series = Series::raw("b", 0, 0).on_device(1);
+        assert_eq!(series.points(&rows, 0..=100), [(100, 2.)]);
+        assert!(series.on_device(2).points(&rows, 0..=100).is_empty());
+    }
+
+    #[test]
+    fn test_filter_reads_every_device() {
+        let mut rows = [History::new(2), History::new(2)];
+        for (device, value) in [(0, 1.), (1, 2.)] {
+            rows[device].push(Row {
+                timestamp: 100,
+                values: vec![value],
+            });
+        }
+        let mut chart = TimeSeriesChart::new(
+            "overlay",
+            vec![Series::raw("a", 0, 0), Series::raw("b", 0, 1).on_device(1)],
+        );
+        let kinds = [FilterKind::Median { window: 1 }];
+        chart.set_filter(&kinds, |device| rows.get(device));
+        let rows: Vec<&History<Row>> = rows.iter().collect();
+        let filtered: Vec<Vec<(u64, f64)>> = chart.series[2..]
+            .iter()
+            .map(|s| s.points(&rows, 0..=100))
+            .collect();
+        assert_eq!(filtered, [[(100, 1.)], [(100, 2.)]]);
+
+        chart.set_style(ChartStyle {
+            filtered: false,
+            ..Default::default()
+        });
+        chart.set_filter(&kinds, |device| rows.get(device).copied());
+        assert_eq!(chart.series.len(), 2);
+    }
+
+    #[test]
+    fn test_cursor_lookup() {
+        let area = PlotArea {
+            px: (40, 240),
+            py: (10, 110),
+            x: (1., 3.),
+            y: (-1., 1.),
+            legend: vec![(String::from("Y"), (50, 20, 90, 40))],
+        };
+        assert_eq!(area.legend_at(Point::new(60., 30.)), Some("Y"));
+        assert_eq!(area.legend_at(Point::new(60., 40.)), None);
+        let p = area.position_of((1.5, 0.5));
+        assert_eq!(p, Point::new(90., 35.));
+        assert_eq!(area.value_at(p), Some((1.5, 0.5)));
+
+        let points = [(1000, 0.), (1100, 1.), (1300, 2.)];
+        assert_eq!(nearest(&points, 1180), Some((1100, 1.)));
+        assert_eq!(nearest(&points, 1220), Some((1300, 2.)));
+        assert_eq!(nearest(&points, 5000), Some((1300, 2.)));
+        assert_eq!(nearest(&[], 5000), None);
+    }
+
+    #[test]
+    fn test_shared_axis_range() {
+        let chart = TimeSeriesChart::xyz("acc", [0, 1, 2]);
+        let mut axis = TimeAxis::default();
+        assert_eq!(chart.x_range(&axis, 2000), (0., 5.));
+        assert_eq!(chart.x_range(&axis, 8000), (3., 8.));
+
+        axis.span = Some(2.);
+        axis.end = Some(4.);
+        assert_eq!(chart.x_range(&axis, 8000), (2., 4.));
     }
 }
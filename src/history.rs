@@ -0,0 +1,175 @@
+use std::{
+    collections::{vec_deque, VecDeque},
+    fs::File,
+    io::{self, BufWriter, Write},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    datasource::{read_file, Columns, Data},
+    derived::Row,
+};
+
+/// Sample that can be kept in a [`History`]
+pub trait Timestamped {
+    fn timestamp(&self) -> u64;
+}
+
+impl Timestamped for Data {
+    fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+}
+
+impl Timestamped for Row {
+    fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+}
+
+/// File the samples evicted from a [`History`] are appended to
+struct Spill<T> {
+    path: PathBuf,
+    out: BufWriter<File>,
+    write: fn(&mut BufWriter<File>, &T) -> io::Result<()>,
+    count: usize,
+}
+
+/// Ring buffer of the last `capacity` samples, pushed in timestamp order.
+/// Samples falling out of the buffer are dropped, or appended to a spill
+/// file when one is set up.
+pub struct History<T> {
+    items: VecDeque<T>,
+    capacity: usize,
+    spill: Option<Spill<T>>,
+}
+
+impl<T: Timestamped> History<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            items: VecDeque::with_capacity(capacity),
+            capacity,
+            spill: None,
+        }
+    }
+
+    pub fn push(&mut self, item: T) {
+        if self.items.len() == self.capacity {
+            if let Some(evicted) = self.items.pop_front() {
+                self.spill_item(&evicted);
+            }
+        }
+        self.items.push_back(item);
+    }
+
+    fn spill_item(&mut self, item: &T) {
+        let Some(spill) = &mut self.spill else {
+            return;
+        };
+        match (spill.write)(&mut spill.out, item) {
+            Ok(()) => spill.count += 1,
+            Err(e) => {
+                eprintln!("failed to write {}: {e}", spill.path.display());
+                self.spill = None;
+            }
+        }
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.items.back()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn iter(&self) -> vec_deque::Iter<'_, T> {
+        self.items.iter()
+    }
+
+    /// Samples in memory with timestamps within `range`
+    pub fn range(&self, range: RangeInclusive<u64>) -> vec_deque::Iter<'_, T> {
+        let start = self
+            .items
+            .partition_point(|i| i.timestamp() < *range.start());
+        let end = self
+            .items
+            .partition_point(|i| i.timestamp() <= *range.end());
+        self.items.range(start..end.max(start))
+    }
+
+    /// Number of samples written to the spill file so far
+    pub fn spilled(&self) -> usize {
+        self.spill.as_ref().map_or(0, |s| s.count)
+    }
+}
+
+impl History<Data> {
+    /// Keep the evicted samples in `path`, in the input file format.
+    /// The file is truncated.
+    pub fn with_spill(mut self, path: &impl AsRef<Path>) -> io::Result<Self> {
+        self.spill = Some(Spill {
+            path: PathBuf::from(path.as_ref()),
+            out: BufWriter::new(File::create(path)?),
+            write: |out, d| {
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{}",
+                    d.timestamp, d.acc.x, d.acc.y, d.acc.z, d.mag.x, d.mag.y, d.mag.z
+                )
+            },
+            count: 0,
+        });
+        Ok(self)
+    }
+
+    /// Every sample of the session, the spilled ones read back from disk
+    pub fn all(&mut self) -> io::Result<Vec<Data>> {
+        let mut data = match &mut self.spill {
+            Some(spill) => {
+                spill.out.flush()?;
+                read_file(&spill.path, &Columns::default())?
+            }
+            None => vec![],
+        };
+        data.extend(self.items.iter().cloned());
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(timestamp: u64) -> Data {
+        Data {
+            timestamp,
+            acc: crate::datasource::AccData {
+                x: timestamp as f64 / 10.,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_bounded_with_spill() {
+        let path = std::env::temp_dir().join("aeroplot-history-test.csv");
+        let mut history = History::new(3).with_spill(&path).unwrap();
+        for t in (0..10).map(|i| i * 100) {
+            history.push(data(t));
+        }
+
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.spilled(), 7);
+        let range: Vec<u64> = history.range(750..=900).map(|d| d.timestamp).collect();
+        assert_eq!(range, [800, 900]);
+        assert_eq!(history.range(0..=100).count(), 0);
+
+        let all = history.all().unwrap();
+        assert_eq!(all.len(), 10);
+        assert_eq!((all[3].timestamp, all[3].acc.x), (300, 30.));
+        std::fs::remove_file(path).unwrap();
+    }
+}
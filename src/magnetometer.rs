@@ -1,10 +1,19 @@
-use iced::widget::canvas::Cache;
+use crate::datasource::MagData;
 
 // functions to:
 // - calculate vector length/ magnetic strength
 // to be presented along the charts
 
-// TODO later
-struct Direction3DChart {
-    cache: Cache,
+impl MagData {
+    pub fn magnitude(&self) -> f64 {
+        (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+    }
+
+    /// Heading in degrees 0..360 assuming the sensor lies flat
+    pub fn heading(&self) -> f64 {
+        self.y.atan2(self.x).to_degrees().rem_euclid(360.)
+    }
 }
+
+// TODO later
+// struct Direction3DChart
@@ -1,118 +1,1146 @@
-use std::{pin::pin, vec};
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    pin::Pin,
+    time::SystemTime,
+    vec,
+};
 
 use iced::{
     executor,
-    futures::{SinkExt, TryFutureExt},
-    time::Duration,
+    futures::FutureExt,
+    futures::SinkExt,
+    keyboard,
+    time::{Duration, Instant},
     widget::{
         button,
         canvas::{Cache, Frame, Geometry},
-        column, row, text, Column, Container, Scrollable, Text,
+        column, pick_list, row, text, text_input, Column, Container, Scrollable,
     },
-    window::Action,
     Alignment, Application, Command, Element, Length, Settings, Size, Subscription, Theme,
 };
 use plotters::prelude::ChartBuilder;
 // use plotters_backend::DrawingBackend;
 use plotters_iced::{plotters_backend::DrawingBackend, Chart, ChartWidget, Renderer};
-use tokio::time::Instant;
 use tokio_stream::{Stream, StreamExt};
 
-use datasource::{stream_file, Data};
-use generic::{AggregateValue2DChart, CurrentValue2DChart};
+use accelerometer::{MotionEvent, STANDARD_GRAVITY};
+use alarm::{AlarmEngine, AlarmEvent};
+use clock::{format_wall_clock, Clock};
+use config::{ChartId, Config, SourceConfig, CONFIG_FILE};
+use datasource::{stream_file, Data, Interpolation, QualityEvent, Resampled, Resampler, Sequencer};
+use derived::{Processor, Row};
+use device::Device;
+use export::{export, ExportFormat};
+use filter::FilterKind;
+use generator::GeneratorConfig;
+use generic::{Series, Source, SpectrumChart, TimeAxis, TimeSeriesChart, Transform};
+use history::History;
+use marker::Marker;
+use overview::Overview;
+use report::report;
+use spectrogram::{Spectrogram, SpectrogramSource};
+use spectrum::Window;
+use stats::Statistics;
+use style::{AppTheme, ChartStyle};
 
 mod accelerometer;
+mod alarm;
+mod calibration;
+mod clock;
+mod config;
 mod datasource;
+mod derived;
+mod device;
+mod downsample;
+mod export;
+mod expression;
+mod filter;
+mod generator;
 mod generic;
+mod history;
 mod magnetometer;
+mod marker;
+mod overview;
+mod report;
+mod spectrogram;
+mod spectrum;
+mod stats;
+mod style;
 
-const TEST_INPUT: &str = "test-input.csv";
+/// samples kept in memory, one hour at 10Hz
+const HISTORY_CAPACITY: usize = 36_000;
+/// window of the rolling statistics
+const STATS_WINDOW_MS: u64 = 5000;
+const ALARM_LOG: &str = "aeroplot-alarms.log";
+/// charts whose style can be picked and saved
+const TIME_SERIES_CHARTS: [ChartId; 6] = [
+    ChartId::Accelerometer,
+    ChartId::Speed,
+    ChartId::Heading,
+    ChartId::Magnetometer,
+    ChartId::Derived,
+    ChartId::Overlay,
+];
 
+/// `aeroplot [--config <file.toml>] [--source <input.csv>] [--resample <hz>|none]
+/// [--frame-rate <hz>] [--theme light|dark|high-contrast]`,
+/// or `aeroplot [--config <file.toml>] export|report ...`
 fn main() {
-    let _c = State::run(Settings::default());
+    let fail = |e: String| -> ! {
+        eprintln!("{e}");
+        std::process::exit(1);
+    };
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let config_path = match args.iter().position(|a| a == "--config") {
+        Some(i) if i + 1 < args.len() => {
+            args.remove(i);
+            PathBuf::from(args.remove(i))
+        }
+        Some(_) => fail(String::from("--config needs a path")),
+        None => PathBuf::from(CONFIG_FILE),
+    };
+    let config =
+        Config::load(&config_path).unwrap_or_else(|e| fail(format!("invalid configuration {e}")));
+    if args.first().map(String::as_str) == Some("export") {
+        match run_export(&args[1..], &config) {
+            Ok(n) => println!("exported {n} rows"),
+            Err(e) => fail(format!("export failed: {e}")),
+        }
+        return;
+    }
+    if args.first().map(String::as_str) == Some("report") {
+        if let Err(e) = run_report(&args[1..], &config) {
+            fail(format!("report failed: {e}"));
+        }
+        return;
+    }
+    let config = override_config(config, &args).unwrap_or_else(|e| fail(e));
+    let _c = State::run(Settings::with_flags((config, config_path)));
+}
+
+/// Command line options take precedence over the configuration file
+fn override_config(mut config: Config, args: &[String]) -> Result<Config, String> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or(format!("{arg} needs a value"))?;
+        match arg.as_str() {
+            "--source" => config.source.path = PathBuf::from(value),
+            "--generate" => {
+                let seed = value.parse().map_err(|_| "--generate needs a seed")?;
+                let generator = config.source.generator.take().unwrap_or_default();
+                config.source.generator = Some(GeneratorConfig { seed, ..generator });
+            }
+            "--resample" if value == "none" => config.source.resample_hz = None,
+            "--resample" => {
+                config.source.resample_hz =
+                    Some(value.parse().map_err(|_| "--resample needs a rate in Hz")?)
+            }
+            "--frame-rate" => {
+                config.frame_rate_hz = value
+                    .parse()
+                    .map_err(|_| "--frame-rate needs a rate in Hz")?
+            }
+            "--theme" => {
+                config.theme = toml::Value::String(value.clone())
+                    .try_into()
+                    .map_err(|_| "--theme is light, dark or high-contrast")?
+            }
+            _ => return Err(format!("unknown option {arg}")),
+        }
+    }
+    config.validate()?;
+    Ok(config)
+}
+
+/// `aeroplot export <input.csv> <output.csv|.jsonl> [--from <ms>] [--to <ms>]
+/// [--resample <hz>] [--interpolation linear|cubic] [--expr <name>=<formula>]...`
+fn run_export(args: &[String], config: &Config) -> Result<usize, String> {
+    let usage = "usage: aeroplot export <input.csv> <output.csv|.jsonl> [--from <ms>] [--to <ms>] \
+                 [--resample <hz>] [--interpolation linear|cubic] [--expr <name>=<formula>]...";
+    let mut positional = vec![];
+    let (mut from, mut to) = (0, u64::MAX);
+    let mut rate = None;
+    let mut interpolation = Interpolation::Linear;
+    let mut processor = config.processor()?;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from" | "--to" => {
+                let value = args
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                    .ok_or(format!("{arg} needs a timestamp in ms"))?;
+                if arg == "--from" {
+                    from = value;
+                } else {
+                    to = value;
+                }
+            }
+            "--resample" => {
+                rate = Some(
+                    args.next()
+                        .and_then(|v| v.parse().ok())
+                        .ok_or("--resample needs a rate in Hz")?,
+                );
+            }
+            "--interpolation" => {
+                interpolation = match args.next().map(String::as_str) {
+                    Some("linear") => Interpolation::Linear,
+                    Some("cubic") => Interpolation::Cubic,
+                    _ => return Err(String::from("--interpolation is linear or cubic")),
+                };
+            }
+            "--expr" => {
+                let (name, formula) = args
+                    .next()
+                    .and_then(|e| e.split_once('='))
+                    .ok_or("--expr needs <name>=<formula>")?;
+                processor.add_expression_channel(name.trim(), formula, "")?;
+            }
+            _ => positional.push(arg),
+        }
+    }
+    let [input, output] = positional[..] else {
+        return Err(String::from(usage));
+    };
+
+    let data = config
+        .source
+        .read(input)
+        .map_err(|e| format!("{input}: {e}"))?;
+    let data: Vec<Data> = if rate.is_some() {
+        let mut resampler = Resampler::new(rate, interpolation, config.source.gap_threshold_ms);
+        data.into_iter()
+            .flat_map(|d| resampler.push(d))
+            .filter_map(|r| match r {
+                Resampled::Data(d) => Some(d),
+                Resampled::Event(_) => None,
+            })
+            .collect()
+    } else {
+        data
+    };
+    let markers = marker::load(input)?;
+    let file = File::create(output).map_err(|e| format!("{output}: {e}"))?;
+    export(
+        &data,
+        from..=to,
+        &mut processor,
+        &markers,
+        ExportFormat::from_path(output),
+        BufWriter::new(file),
+    )
+    .map_err(|e| format!("{output}: {e}"))
+}
+
+/// `aeroplot report <input.csv> [<output.txt>]`, to stdout without an output
+fn run_report(args: &[String], config: &Config) -> Result<(), String> {
+    let (input, output) = match args {
+        [input] => (input, None),
+        [input, output] => (input, Some(output)),
+        _ => {
+            return Err(String::from(
+                "usage: aeroplot report <input.csv> [<output.txt>]",
+            ))
+        }
+    };
+    let data = config
+        .source
+        .read(input)
+        .map_err(|e| format!("{input}: {e}"))?;
+    let mut resampler = Resampler::new(
+        config.source.resample_hz,
+        Interpolation::Linear,
+        config.source.gap_threshold_ms,
+    );
+    let mut processor = config.processor()?;
+    let mut alarms = AlarmEngine::new(config.alarms.clone(), processor.channels())?;
+    let markers = marker::load(input)?;
+    match output {
+        Some(output) => {
+            let file = File::create(output).map_err(|e| format!("{output}: {e}"))?;
+            report(
+                &data,
+                &mut resampler,
+                &mut processor,
+                &mut alarms,
+                &markers,
+                BufWriter::new(file),
+            )
+            .map_err(|e| format!("{output}: {e}"))
+        }
+        None => report(
+            &data,
+            &mut resampler,
+            &mut processor,
+            &mut alarms,
+            &markers,
+            io::stdout().lock(),
+        )
+        .map_err(|e| e.to_string()),
+    }
 }
 
 struct State {
     value: i32,
-    input_values: Vec<Data>,
+    config: Config,
+    /// where "save setup" writes `config` to
+    config_path: PathBuf,
+    /// resampled input, older samples spill to [`Config::history_spill`]
+    history: History<Data>,
+    /// processed `history`, read by the time series charts
+    rows: History<Row>,
     chart: MyChart,
     chart2: My3DChart,
+    status: String,
+    gaps: Vec<(u64, u64)>,
+    processor: Processor,
+    alarms: AlarmEngine,
+    /// latest state of every alarm, oldest first
+    alarm_events: Vec<AlarmEvent>,
+    alarm_log: Option<File>,
+    motion_events: Vec<MotionEvent>,
     // accelerometer values\
     // accelerometer_calculated_speed
-    acc_current_chart: CurrentValue2DChart,
-    // acc_speed_chart: AggregateValue2DChart,
-    // acc_position_chart: AggregateValue2DChart,
-    mag_current_chart: CurrentValue2DChart,
+    acc_current_chart: TimeSeriesChart,
+    acc_spectrum_chart: SpectrumChart,
+    acc_spectrogram: Spectrogram,
+    speed_chart: TimeSeriesChart,
+    heading_chart: TimeSeriesChart,
+    mag_current_chart: TimeSeriesChart,
+    derived_chart: TimeSeriesChart,
+    /// session time of the main source
+    clock: Clock,
+    /// order of the main source timestamps
+    sequencer: Sequencer,
+    /// the other devices of [`Config::devices`], in order
+    devices: Vec<Device>,
+    /// one channel of every device
+    overlay_chart: TimeSeriesChart,
+    /// the whole recording, selects the range of the time series charts
+    overview: Overview,
+    /// time range and cursor of all time series charts
+    axis: TimeAxis,
+    /// labelled points of the recording, sorted by timestamp
+    markers: Vec<Marker>,
+    /// label of the next marker added
+    marker_label: String,
+    resampler: Resampler,
+    /// statistics of the samples as received, before resampling
+    stats: Statistics,
+    /// arrival of the oldest sample not drawn yet
+    pending: Option<Instant>,
+    /// frames drawn within the last second
+    frames: VecDeque<Instant>,
+    /// from receiving a sample to the frame drawing it
+    latency: Duration,
+    /// recordings opened so far, a new one restarts the source subscription
+    replay: u64,
     // magnetometer values
     // accelerometer_calculated_power
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
-    ReceivedNewData(Data),
+    /// samples and quality events drained from the source of a device at
+    /// once, with the time the first one was received. Device 0 is the main
+    /// source.
+    ReceivedBatch(usize, Instant, Vec<Result<Data, QualityEvent>>),
+    /// the source of a device ran out of samples in the given replay
+    SourceEnded(usize, u64),
+    /// redraw the charts if anything changed
+    Frame(Instant),
+    Export(ExportFormat),
+    FilterSelected(Option<FilterKind>),
+    SpectrumWindowSelected(Window),
+    SpectrogramSourceSelected(SpectrogramSource),
+    ChartStyleChanged(ChartId, ChartStyle),
+    ThemeSelected(AppTheme),
+    /// write the current setup to the configuration file
+    SaveSetup,
+    OpenDialog,
+    /// decimated trace of the recording opened as the given replay
+    OverviewLoaded(u64, Result<Vec<(f64, f64)>, String>),
+    /// time range brushed on the overview, `None` follows the latest samples
+    RangeSelected(Option<(u64, u64)>),
+    /// zoomed, panned or hovered in one of the time series charts
+    AxisChanged(TimeAxis),
+    /// replay a recording, `None` when the dialog was cancelled
+    Open(Option<PathBuf>),
+    OverlayChannelSelected(String),
+    MarkerLabelChanged(String),
+    /// mark a timestamp in ms, `None` marks the latest sample
+    AddMarker(Option<u64>),
     Increment,
     Decrement,
     Tick,
 }
 
+impl State {
+    /// Fresh charts and processing for replaying `config.source`
+    fn with_config(config: Config, config_path: PathBuf) -> Self {
+        let processor = config
+            .processor()
+            .expect("configuration is validated on load");
+        let index = |name: &str| processor.channels().iter().position(|c| c.name == name);
+        let [acc_x, acc_y, acc_z, mag_x, mag_y, mag_z] =
+            ["acc_x", "acc_y", "acc_z", "mag_x", "mag_y", "mag_z"]
+                .map(|name| index(name).expect("built-in channel"));
+        let [speed_x, speed_y, speed_z, heading] = ["speed_x", "speed_y", "speed_z", "heading"]
+            .map(|name| index(name).expect("built-in channel"));
+        let derived: Vec<Series> = config
+            .channels
+            .iter()
+            .take(3)
+            .filter_map(|c| index(&c.name).map(|i| (&c.name, i)))
+            .enumerate()
+            .map(|(i, (name, c))| Series::raw(name, c, i))
+            .collect();
+        let title = |id: ChartId, default: &str| {
+            config
+                .chart(id)
+                .title
+                .unwrap_or_else(|| String::from(default))
+        };
+        // drift of the plain integral next to the bias corrected speed
+        let uncorrected = |label: &str, channel: usize, color: usize| {
+            Series::new(
+                label,
+                Source::Channel(channel),
+                Transform::integrated(STANDARD_GRAVITY),
+                color,
+            )
+        };
+        let history = History::new(HISTORY_CAPACITY);
+        let history = match &config.history_spill {
+            Some(path) => history.with_spill(path).unwrap_or_else(|e| {
+                eprintln!(
+                    "failed to create {}, older samples are dropped: {e}",
+                    path.display()
+                );
+                History::new(HISTORY_CAPACITY)
+            }),
+            None => history,
+        };
+        let alarms = AlarmEngine::new(config.alarms.clone(), processor.channels())
+            .expect("configuration is validated on load");
+        let alarm_log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(ALARM_LOG)
+            .map_err(|e| eprintln!("failed to open {ALARM_LOG}: {e}"))
+            .ok();
+        let devices = config
+            .devices
+            .iter()
+            .enumerate()
+            .map(|(i, source)| {
+                Device::new(i + 1, source.clone(), &config)
+                    .expect("configuration is validated on load")
+            })
+            .collect();
+        let overlay_chart = overlay_chart(&config, &processor);
+        // markers of a generated source only live as long as the session
+        let loaded = match config.source.generator {
+            Some(_) => Ok(vec![]),
+            None => marker::load(&config.source.path),
+        };
+        let (markers, status) = match loaded {
+            Ok(markers) => (markers, String::new()),
+            Err(e) => (vec![], format!("failed to load the markers: {e}")),
+        };
+        let mut state = State {
+            value: 1,
+            history,
+            rows: History::new(HISTORY_CAPACITY),
+            chart: MyChart::default(),
+            chart2: My3DChart::default(),
+            status,
+            gaps: vec![],
+            processor,
+            alarms,
+            alarm_events: vec![],
+            alarm_log,
+            motion_events: vec![],
+            acc_current_chart: TimeSeriesChart::xyz(
+                &title(ChartId::Accelerometer, "Accelerometer current raw value"),
+                [acc_x, acc_y, acc_z],
+            ),
+            acc_spectrum_chart: SpectrumChart::with_title(
+                &title(ChartId::Spectrum, "Accelerometer spectrum"),
+                [acc_x, acc_y, acc_z],
+                64,
+            ),
+            acc_spectrogram: Spectrogram::new(
+                &title(ChartId::Spectrogram, "Accelerometer spectrogram"),
+                [acc_x, acc_y, acc_z],
+                64,
+                16,
+                300,
+            ),
+            speed_chart: TimeSeriesChart::new(
+                &title(ChartId::Speed, "Speed [m/s]"),
+                vec![
+                    Series::raw("X", speed_x, 0),
+                    Series::raw("Y", speed_y, 1),
+                    Series::raw("Z", speed_z, 2),
+                    uncorrected("X uncorrected", acc_x, 0),
+                    uncorrected("Y uncorrected", acc_y, 1),
+                ],
+            ),
+            heading_chart: TimeSeriesChart::new(
+                &title(ChartId::Heading, "Heading [deg]"),
+                vec![Series::raw("heading", heading, 0)],
+            )
+            .with_y_range(0., 360.),
+            mag_current_chart: TimeSeriesChart::new(
+                &title(ChartId::Magnetometer, "Magnetometer current raw value"),
+                vec![
+                    Series::raw("X", mag_x, 0),
+                    Series::raw("Y", mag_y, 1),
+                    Series::raw("Z", mag_z, 2),
+                ],
+            )
+            .with_y_range(-1., 1.),
+            derived_chart: TimeSeriesChart::new(
+                &title(ChartId::Derived, "Derived channels"),
+                derived,
+            ),
+            clock: Clock::new(config.source.clock),
+            sequencer: Sequencer::new(config.source.order, config.source.clock.reset_threshold_ms),
+            devices,
+            overlay_chart,
+            overview: Overview::new(config.theme),
+            axis: TimeAxis::default(),
+            markers,
+            marker_label: String::new(),
+            resampler: Resampler::new(
+                config.source.resample_hz,
+                Interpolation::Linear,
+                config.source.gap_threshold_ms,
+            ),
+            stats: Statistics::new(STATS_WINDOW_MS),
+            pending: None,
+            frames: VecDeque::new(),
+            latency: Duration::ZERO,
+            replay: 0,
+            config,
+            config_path,
+        };
+        for id in TIME_SERIES_CHARTS {
+            let style = state.config.chart(id).style;
+            if let Some(chart) = state.time_series_chart(id) {
+                chart.set_style(style);
+            }
+        }
+        for device in &mut state.devices {
+            for (chart, id) in device
+                .charts()
+                .into_iter()
+                .zip([ChartId::Accelerometer, ChartId::Magnetometer])
+            {
+                chart.set_style(state.config.chart(id).style);
+            }
+        }
+        state.apply_filters();
+        state.apply_theme();
+        state.overview.set_markers(&state.markers);
+        state
+    }
+
+    /// Processor for exports, with filtered channels when filters are selected
+    fn export_processor(&self) -> Processor {
+        let mut processor = self
+            .config
+            .processor()
+            .expect("configuration is validated on load");
+        if !self.config.filters.is_empty() {
+            for channel in [
+                "acc_x", "acc_y", "acc_z", "acc_mag", "mag_x", "mag_y", "mag_z",
+            ] {
+                processor.add_filtered_channel(channel, &self.config.filters);
+            }
+        }
+        processor
+    }
+
+    fn handle_alarms(&mut self, events: Vec<AlarmEvent>) {
+        for event in events {
+            if let Some(log) = &mut self.alarm_log {
+                if let Err(e) = writeln!(log, "{event}") {
+                    eprintln!("failed to write {ALARM_LOG}: {e}");
+                    self.alarm_log = None;
+                }
+            }
+            for chart in self.time_series_charts() {
+                chart.push_alarm(&event);
+            }
+            match self
+                .alarm_events
+                .iter_mut()
+                .find(|e| e.rule == event.rule && e.start == event.start && e.end.is_none())
+            {
+                Some(e) => *e = event,
+                None => self.alarm_events.push(event),
+            }
+        }
+    }
+
+    fn time_series_chart(&mut self, id: ChartId) -> Option<&mut TimeSeriesChart> {
+        match id {
+            ChartId::Accelerometer => Some(&mut self.acc_current_chart),
+            ChartId::Speed => Some(&mut self.speed_chart),
+            ChartId::Heading => Some(&mut self.heading_chart),
+            ChartId::Magnetometer => Some(&mut self.mag_current_chart),
+            ChartId::Derived => Some(&mut self.derived_chart),
+            ChartId::Overlay => Some(&mut self.overlay_chart),
+            ChartId::Spectrum | ChartId::Spectrogram | ChartId::Devices | ChartId::Demo => None,
+        }
+    }
+
+    fn time_series_charts(&mut self) -> [&mut TimeSeriesChart; 5] {
+        [
+            &mut self.acc_current_chart,
+            &mut self.speed_chart,
+            &mut self.heading_chart,
+            &mut self.mag_current_chart,
+            &mut self.derived_chart,
+        ]
+    }
+
+    /// Charts of the other devices and the overlay, these are not fed the
+    /// gaps and alarms of the main source
+    fn device_charts(&mut self) -> impl Iterator<Item = &mut TimeSeriesChart> {
+        std::iter::once(&mut self.overlay_chart)
+            .chain(self.devices.iter_mut().flat_map(|d| d.charts()))
+    }
+
+    /// Rows of every device, the main source first
+    fn device_rows(&self) -> Vec<&History<Row>> {
+        std::iter::once(&self.rows)
+            .chain(self.devices.iter().map(|d| &d.rows))
+            .collect()
+    }
+
+    /// Overlay the filter chain of the [`Config`] on the time series charts
+    /// whose style shows it
+    fn apply_filters(&mut self) {
+        let filters = &self.config.filters;
+        let main = |device: usize| (device == 0).then_some(&self.rows);
+        for chart in [
+            &mut self.acc_current_chart,
+            &mut self.speed_chart,
+            &mut self.heading_chart,
+            &mut self.mag_current_chart,
+            &mut self.derived_chart,
+        ] {
+            chart.set_filter(filters, main);
+        }
+        self.overlay_chart
+            .set_filter(filters, |device| match device {
+                0 => Some(&self.rows),
+                device => self.devices.get(device - 1).map(|d| &d.rows),
+            });
+        for device in &mut self.devices {
+            device.set_filter(filters);
+        }
+    }
+
+    /// Copy the styles picked in the charts into the [`Config`]
+    fn keep_styles(&mut self) {
+        for id in TIME_SERIES_CHARTS {
+            if let Some(style) = self.time_series_chart(id).map(|c| c.style()) {
+                self.config.charts.entry(id).or_default().style = style;
+            }
+        }
+    }
+
+    /// Stop the current source and replay `path` from the start
+    fn open(&mut self, path: PathBuf) {
+        if path.extension().and_then(|e| e.to_str()) != Some("csv") {
+            self.status = format!("{} is not a CSV recording", path.display());
+            return;
+        }
+        // the alarms of the samples held back still reach the log
+        for device in 0..=self.devices.len() {
+            self.finish_source(device);
+        }
+        self.keep_styles();
+        let mut config = self.config.clone();
+        config.source.path = path.clone();
+        config.source.generator = None;
+        config.add_recent(path.clone());
+        // only the recent files change on disk, the rest is saved on request
+        let saved = Config::load(&self.config_path).and_then(|mut saved| {
+            saved.add_recent(path.clone());
+            saved.save(&self.config_path)
+        });
+        // the old spill file has to be closed before the new one truncates it
+        self.history = History::new(0);
+        let replay = self.replay + 1;
+        *self = State::with_config(config, self.config_path.clone());
+        self.replay = replay;
+        self.status = match saved {
+            Ok(()) => format!("replaying {}", path.display()),
+            Err(e) => format!(
+                "replaying {}, failed to update the recent files: {e}",
+                path.display()
+            ),
+        };
+    }
+
+    /// Draw the charts with the theme of the [`Config`]
+    fn apply_theme(&mut self) {
+        let theme = self.config.theme;
+        for chart in self.time_series_charts() {
+            chart.set_theme(theme);
+        }
+        for chart in self.device_charts() {
+            chart.set_theme(theme);
+        }
+        self.acc_spectrum_chart.set_theme(theme);
+        self.overview.set_theme(theme);
+    }
+
+    /// Move every time series chart to `axis`, the overview shows the
+    /// range when it is not following the latest samples
+    fn set_axis(&mut self, axis: TimeAxis) {
+        // the cursor is drawn over the cached charts
+        let moved = TimeAxis {
+            cursor: self.axis.cursor,
+            ..axis
+        } != self.axis;
+        self.axis = axis;
+        if !moved {
+            return;
+        }
+        for chart in self.time_series_charts() {
+            chart.redraw();
+        }
+        for chart in self.device_charts() {
+            chart.redraw();
+        }
+        let latest = self.rows.last().map_or(0, |r| r.timestamp);
+        let (start, end) = self.acc_current_chart.x_range(&axis, latest);
+        self.overview.set_selection(
+            axis.end
+                .map(|_| ((start.max(0.) * 1000.) as u64, (end.max(0.) * 1000.) as u64)),
+        );
+    }
+
+    /// Accelerometer and magnetometer values of the last row at or before
+    /// the shared cursor
+    fn cursor_readout(&self) -> Option<String> {
+        let t = (self.axis.cursor?.max(0.) * 1000.) as u64;
+        let row = self.rows.range(0..=t).next_back()?;
+        let channels = self.processor.channels();
+        let values = ["acc_x", "acc_y", "acc_z", "mag_x", "mag_y", "mag_z"]
+            .iter()
+            .filter_map(|name| {
+                let i = channels.iter().position(|c| c.name == *name)?;
+                Some(format!("{name} {:.4}{}", row.values[i], channels[i].unit))
+            })
+            .collect::<Vec<String>>();
+        let time = match self.axis.origin {
+            Some(origin) => format_wall_clock(origin + row.timestamp),
+            None => format!("{:.3}s", row.timestamp as f64 / 1000.),
+        };
+        Some(format!("{time}  {}", values.join("  ")))
+    }
+
+    /// Read the whole recording in the background for the [`Overview`]
+    fn load_overview(&self) -> Command<Message> {
+        if self.config.source.generator.is_some() {
+            return Command::none();
+        }
+        let replay = self.replay;
+        let source = self.config.source.clone();
+        let calibration = self.config.calibration.clone().with_units(source.units);
+        Command::perform(
+            async move {
+                tokio::task::spawn_blocking(move || {
+                    overview::trace(&source, &calibration)
+                        .map_err(|e| format!("{}: {e}", source.path.display()))
+                })
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|trace| trace)
+            },
+            move |trace| Message::OverviewLoaded(replay, trace),
+        )
+    }
+
+    fn chart_view(&self, id: ChartId) -> Element<'_, Message> {
+        match id {
+            ChartId::Accelerometer => self.time_series_view(id, &self.acc_current_chart),
+            ChartId::Speed => self.time_series_view(id, &self.speed_chart),
+            ChartId::Heading => self.time_series_view(id, &self.heading_chart),
+            ChartId::Magnetometer => self.time_series_view(id, &self.mag_current_chart),
+            ChartId::Derived => self.time_series_view(id, &self.derived_chart),
+            ChartId::Devices => self
+                .devices
+                .iter()
+                .fold(column![], |group, device| {
+                    group.push(row![
+                        device
+                            .acc_chart
+                            .view(self.device_rows(), &self.axis, &self.markers),
+                        device
+                            .mag_chart
+                            .view(self.device_rows(), &self.axis, &self.markers),
+                    ])
+                })
+                .into(),
+            ChartId::Overlay => {
+                let channels: Vec<String> = self
+                    .processor
+                    .channels()
+                    .iter()
+                    .map(|c| c.name.clone())
+                    .collect();
+                column![
+                    pick_list(
+                        channels,
+                        Some(self.config.overlay_channel.clone()),
+                        Message::OverlayChannelSelected
+                    ),
+                    self.time_series_view(id, &self.overlay_chart),
+                ]
+                .align_items(Alignment::Center)
+                .into()
+            }
+            ChartId::Spectrum => column![
+                pick_list(
+                    &Window::ALL[..],
+                    Some(self.acc_spectrum_chart.window()),
+                    Message::SpectrumWindowSelected
+                ),
+                self.acc_spectrum_chart.view(),
+            ]
+            .align_items(Alignment::Center)
+            .into(),
+            ChartId::Spectrogram => column![
+                pick_list(
+                    &SpectrogramSource::ALL[..],
+                    Some(self.acc_spectrogram.source()),
+                    Message::SpectrogramSourceSelected
+                ),
+                self.acc_spectrogram.view(&self.axis, &self.markers),
+            ]
+            .align_items(Alignment::Center)
+            .into(),
+            ChartId::Demo => row![self.chart.view(), self.chart2.view()].into(),
+        }
+    }
+
+    fn time_series_view<'a>(
+        &'a self,
+        id: ChartId,
+        chart: &'a TimeSeriesChart,
+    ) -> Element<'a, Message> {
+        column![
+            chart.style_controls(move |s| Message::ChartStyleChanged(id, s)),
+            chart.view(self.device_rows(), &self.axis, &self.markers),
+        ]
+        .align_items(Alignment::Center)
+        .into()
+    }
+
+    fn redraw(&mut self) {
+        for chart in self.time_series_charts() {
+            chart.redraw();
+        }
+        for chart in self.device_charts() {
+            chart.redraw();
+        }
+        self.acc_spectrum_chart.refresh(&self.rows);
+        self.acc_spectrogram.redraw();
+        if let Some(row) = self.rows.last() {
+            self.overview.set_latest(row.timestamp);
+        }
+    }
+
+    fn handle_data(&mut self, d: Data) {
+        let row = self.processor.process(&d);
+        self.acc_spectrogram.push_row(&row);
+        let mut events = self.alarms.process(&row);
+        for motion in self.processor.take_motion_events() {
+            events.extend(motion.as_alarm());
+            self.motion_events.push(motion);
+        }
+        self.handle_alarms(events);
+        for chart in self.time_series_charts() {
+            chart.push_row(0, &row);
+        }
+        self.overlay_chart.push_row(0, &row);
+        self.rows.push(row);
+        self.history.push(d);
+    }
+
+    /// Samples of the main source passed on by the sequencer, in session time
+    /// from here
+    fn handle_ordered(&mut self, ordered: Vec<Data>, received_at: SystemTime) {
+        for d in ordered {
+            let d = self.clock.rebase(d);
+            let Some(d) = self.config.source.align(d) else {
+                continue;
+            };
+            self.clock.received(d.timestamp, received_at);
+            self.handle_sample(d);
+        }
+    }
+
+    /// Process the samples the sequencer of `device` still holds back
+    fn finish_source(&mut self, device: usize) {
+        if device == 0 {
+            let ordered = self.sequencer.finish();
+            self.handle_ordered(ordered, SystemTime::now());
+            return;
+        }
+        let Some(source) = self.devices.get_mut(device - 1) else {
+            return;
+        };
+        for row in source.finish() {
+            self.overlay_chart.push_row(device, &row);
+        }
+    }
+
+    /// Sample of the main source in order, before resampling
+    fn handle_sample(&mut self, d: Data) {
+        self.stats.push(&d);
+        for r in self.resampler.push(d) {
+            match r {
+                Resampled::Data(d) => self.handle_data(d),
+                Resampled::Event(e) => self.handle_quality_event(e),
+            }
+        }
+    }
+
+    /// Data of another device, to its own charts and the overlay
+    fn handle_device_data(&mut self, device: usize, d: Data) {
+        let Some(source) = self.devices.get_mut(device - 1) else {
+            return;
+        };
+        for row in source.push(d) {
+            self.overlay_chart.push_row(device, &row);
+        }
+    }
+
+    fn handle_quality_event(&mut self, event: QualityEvent) {
+        match &event {
+            QualityEvent::Gap { start, end } => {
+                for chart in self.time_series_charts() {
+                    chart.push_gap(*start, *end);
+                }
+                self.gaps.push((*start, *end));
+            }
+            QualityEvent::MalformedLine { line, reason } => {
+                eprintln!("skipping line {line}: {reason}");
+                self.stats.dropped_lines += 1;
+            }
+        }
+        let events = self.alarms.quality_event(&event);
+        self.handle_alarms(events);
+    }
+}
+
 impl Application for State {
     type Executor = executor::Default;
-    type Flags = ();
+    type Flags = (Config, PathBuf);
     type Message = Message;
     type Theme = Theme;
 
-    fn new(_flags: Self::Flags) -> (Self, Command<Self::Message>) {
-        (
-            State {
-                value: 1,
-                input_values: vec![],
-                chart: MyChart::default(),
-                chart2: My3DChart::default(),
-                acc_current_chart: CurrentValue2DChart::with_title(
-                    "Accelerometer current raw value",
-                ),
-                mag_current_chart: CurrentValue2DChart::with_title(
-                    "Magnetometer current raw value",
-                ),
-            },
-            Command::none(),
-        )
+    fn new((config, config_path): Self::Flags) -> (Self, Command<Self::Message>) {
+        let state = State::with_config(config, config_path);
+        let load = state.load_overview();
+        (state, load)
     }
 
     fn title(&self) -> String {
         String::from("aeroplot")
     }
 
-    fn view(&self) -> Element<Message> {
-        let data_str = format!("{}", self.input_values.last().unwrap_or(&Data::default()));
+    fn theme(&self) -> Theme {
+        self.config.theme.iced()
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        let data_str = format!("{}", self.history.last().unwrap_or(&Data::default()));
         let buttons = row![
             button("+").on_press(Message::Increment),
             text(self.value).size(20),
             button("-").on_press(Message::Decrement),
-        ];
+            button("Export CSV").on_press(Message::Export(ExportFormat::Csv)),
+            button("Export JSON").on_press(Message::Export(ExportFormat::JsonLines)),
+            pick_list(
+                &FilterKind::PRESETS[..],
+                self.config.filters.first().copied(),
+                |k| Message::FilterSelected(Some(k))
+            )
+            .placeholder("filter"),
+            button("No filter").on_press(Message::FilterSelected(None)),
+            pick_list(
+                &AppTheme::ALL[..],
+                Some(self.config.theme),
+                Message::ThemeSelected
+            ),
+            button("Save setup").on_press(Message::SaveSetup),
+        ]
+        .spacing(10);
+        let recent: Vec<String> = self
+            .config
+            .recent
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect();
+        let source = row![
+            button("Open recording…").on_press(Message::OpenDialog),
+            pick_list(recent, None, |p| Message::Open(Some(PathBuf::from(p))))
+                .placeholder("recent recordings"),
+            text(match &self.config.source.generator {
+                Some(generator) => format!("source: generator, seed {}", generator.seed),
+                None => format!("source: {}", self.config.source.path.display()),
+            })
+            .size(20),
+            text_input("marker label", &self.marker_label)
+                .on_input(Message::MarkerLabelChanged)
+                .on_submit(Message::AddMarker(None))
+                .width(200),
+            button("Add marker").on_press(Message::AddMarker(None)),
+        ]
+        .align_items(Alignment::Center)
+        .spacing(10);
         let x = column![
             buttons,
+            source,
             text(data_str).size(25),
-            text(format!("input data len: {}", self.input_values.len())).size(25),
+            text(format!(
+                "input data len: {} ({} spilled to disk)",
+                self.history.len() + self.history.spilled(),
+                self.history.spilled()
+            ))
+            .size(25),
+            text(match self.gaps.last() {
+                Some((start, end)) => format!(
+                    "gaps: {} (last {:.2}s - {:.2}s)",
+                    self.gaps.len(),
+                    *start as f64 / 1000.,
+                    *end as f64 / 1000.
+                ),
+                None => String::from("gaps: 0"),
+            })
+            .size(20),
+            text(format!(
+                "{} fps, latency {}ms",
+                self.frames.len(),
+                self.latency.as_millis()
+            ))
+            .size(20),
+            text(&self.status).size(20),
         ]
         .padding(20)
         .align_items(iced::Alignment::Center);
 
-        let acc_charts = column![
-            text("Accelerometer charts").size(25),
-            self.acc_current_chart.view(),
-        ]
-        .align_items(Alignment::Center)
-        .height(600);
-        let mag_charts = column![
-            text("Magnetometer charts").size(25),
-            self.mag_current_chart.view()
-        ]
-        .align_items(Alignment::Center)
-        .height(600);
-        let test_charts = row![self.chart.view(), self.chart2.view()].height(600);
-        let chart_container = column![acc_charts, mag_charts, test_charts].padding(20);
-        println!("in view of State");
+        let chart_container = self
+            .config
+            .rows
+            .iter()
+            .fold(column![], |container, layout| {
+                let charts = layout.charts.iter().map(|id| self.chart_view(*id));
+                container
+                    .push(iced::widget::Row::with_children(charts.collect()).height(layout.height))
+            })
+            .padding(20);
+
+        let alarm_panel = self
+            .alarm_events
+            .iter()
+            .rev()
+            .take(10)
+            .fold(column![text("Alarms").size(25)], |panel, event| {
+                let style = if event.end.is_none() {
+                    iced::theme::Text::Color(self.theme().palette().danger)
+                } else {
+                    iced::theme::Text::Default
+                };
+                panel.push(text(event.to_string()).size(18).style(style))
+            })
+            .align_items(Alignment::Center);
+        let bias = self.processor.acc_bias();
+        let motion_panel = self
+            .motion_events
+            .iter()
+            .rev()
+            .take(5)
+            .fold(
+                column![
+                    text("Motion events").size(25),
+                    text(format!(
+                        "integration bias: {:.4},{:.4},{:.4}g",
+                        bias[0], bias[1], bias[2]
+                    ))
+                    .size(18)
+                ],
+                |panel, event| panel.push(text(event.to_string()).size(18)),
+            )
+            .align_items(Alignment::Center);
+
+        let (rate, jitter) = self.stats.rate();
+        let timestamps = self.devices.iter().fold(
+            column![text(format!(
+                "{} timestamps: {}",
+                self.config.source.device, self.sequencer.anomalies
+            ))
+            .size(18)],
+            |panel, device| {
+                panel.push(
+                    text(format!(
+                        "{} timestamps: {}",
+                        device.source.device,
+                        device.anomalies()
+                    ))
+                    .size(18),
+                )
+            },
+        );
+        let stats_panel = self.stats.axes.iter().zip(stats::AXES).fold(
+            column![
+                text("Statistics").size(25),
+                text(format!(
+                    "rate: {rate:.1}Hz, jitter: {jitter:.1}ms, dropped lines: {}, gaps: {}, \
+                     wrap-arounds: {}",
+                    self.stats.dropped_lines,
+                    self.gaps.len(),
+                    self.clock.wraps
+                ))
+                .size(18),
+                timestamps,
+                text(format!(
+                    "{:<6} {:>38} | {:>38}",
+                    "",
+                    format!("last {}s min/max/mean/std/rms", STATS_WINDOW_MS / 1000),
+                    "session min/max/mean/std/rms"
+                ))
+                .size(16)
+                .font(iced::Font::MONOSPACE),
+            ],
+            |panel, (axis, name)| {
+                let w = axis.window.summary();
+                let s = axis.session.summary();
+                panel.push(
+                    text(format!(
+                        "{name:<6} {:>7.3} {:>7.3} {:>7.3} {:>6.3} {:>6.3} | {:>7.3} {:>7.3} {:>7.3} {:>6.3} {:>6.3}",
+                        w.min, w.max, w.mean, w.std_dev, w.rms,
+                        s.min, s.max, s.mean, s.std_dev, s.rms,
+                    ))
+                    .size(16)
+                    .font(iced::Font::MONOSPACE),
+                )
+            },
+        );
 
         let content = Column::new()
             .spacing(10)
@@ -120,11 +1148,22 @@ impl Application for State {
             .width(Length::FillPortion(1))
             .height(Length::FillPortion(1))
             .push(x)
+            .push(row![alarm_panel, motion_panel].spacing(40))
+            .push(stats_panel)
             .push(chart_container);
 
         let scrollable = Scrollable::new(content);
+        // kept above the scrolled charts, like a video scrubber
+        let readout = text(self.cursor_readout().unwrap_or_default())
+            .size(18)
+            .font(iced::Font::MONOSPACE);
+        let page = if self.overview.is_empty() {
+            column![readout, scrollable]
+        } else {
+            column![self.overview.view(), readout, scrollable].spacing(10)
+        };
 
-        Container::new(scrollable)
+        Container::new(page)
             .width(Length::Fill)
             .height(Length::Fill)
             .padding(10)
@@ -144,39 +1183,259 @@ impl Application for State {
             Message::Tick => {
                 self.value += 1;
             }
-            Message::ReceivedNewData(d) => {
-                println!("received data {d:?}");
-                self.acc_current_chart
-                    .push_datapoint(d.timestamp, d.acc.x, d.acc.y, d.acc.z);
-                self.mag_current_chart
-                    .push_datapoint(d.timestamp, d.mag.x, d.mag.y, d.mag.z);
-                self.input_values.push(d);
-                // self.acc_current_chart.update(state, event, bounds, cursor)
+            Message::ReceivedBatch(0, received, batch) => {
+                let received_at = SystemTime::now() - received.elapsed();
+                for item in batch {
+                    match item {
+                        Ok(d) => {
+                            let ordered = self.sequencer.push(self.clock.unwrap(d));
+                            self.handle_ordered(ordered, received_at);
+                        }
+                        Err(e) => self.handle_quality_event(e),
+                    }
+                }
+                self.pending.get_or_insert(received);
+                if self.clock.wall_origin() != self.axis.origin {
+                    self.axis.origin = self.clock.wall_origin();
+                    self.overview.set_origin(self.axis.origin);
+                }
+            }
+            Message::ReceivedBatch(device, received, batch) => {
+                for item in batch {
+                    match item {
+                        Ok(d) => self.handle_device_data(device, d),
+                        Err(QualityEvent::MalformedLine { line, reason }) => {
+                            eprintln!("skipping line {line} of device {device}: {reason}");
+                        }
+                        Err(QualityEvent::Gap { .. }) => {}
+                    }
+                }
+                self.pending.get_or_insert(received);
+            }
+            Message::SourceEnded(device, replay) if replay == self.replay => {
+                self.finish_source(device);
+                self.pending.get_or_insert(Instant::now());
+            }
+            Message::SourceEnded(..) => {}
+            Message::Frame(now) => {
+                if let Some(received) = self.pending.take() {
+                    self.redraw();
+                    self.latency = now.saturating_duration_since(received);
+                    self.frames.push_back(now);
+                }
+                while self
+                    .frames
+                    .front()
+                    .is_some_and(|t| now.duration_since(*t) > Duration::from_secs(1))
+                {
+                    self.frames.pop_front();
+                }
+            }
+            Message::FilterSelected(filter) => {
+                self.config.filters = filter.into_iter().collect();
+                self.apply_filters();
+            }
+            Message::ThemeSelected(theme) => {
+                self.config.theme = theme;
+                self.apply_theme();
+            }
+            Message::SaveSetup => {
+                self.keep_styles();
+                self.status = match self.config.save(&self.config_path) {
+                    Ok(()) => format!("saved the setup to {}", self.config_path.display()),
+                    Err(e) => format!("failed to save the setup: {e}"),
+                };
+            }
+            Message::SpectrumWindowSelected(window) => {
+                self.acc_spectrum_chart.set_window(window);
+            }
+            Message::SpectrogramSourceSelected(source) => {
+                self.acc_spectrogram.set_source(source);
+            }
+            Message::OpenDialog => {
+                let dialog = rfd::AsyncFileDialog::new()
+                    .set_title("Open recording")
+                    .add_filter("CSV recording", &["csv"])
+                    .pick_file();
+                return Command::perform(
+                    async move { dialog.await.map(|file| file.path().to_path_buf()) },
+                    Message::Open,
+                );
+            }
+            Message::Open(Some(path)) => {
+                self.open(path);
+                return self.load_overview();
+            }
+            Message::OverviewLoaded(replay, trace) if replay == self.replay => match trace {
+                Ok(trace) => self.overview.set_trace(trace),
+                Err(e) => self.status = format!("failed to load the overview: {e}"),
+            },
+            Message::OverviewLoaded(..) => {}
+            Message::RangeSelected(selection) => {
+                let (span, end) = match selection {
+                    Some((start, end)) => {
+                        (Some((end - start) as f64 / 1000.), Some(end as f64 / 1000.))
+                    }
+                    None => (None, None),
+                };
+                self.set_axis(TimeAxis {
+                    span,
+                    end,
+                    ..self.axis
+                });
+            }
+            Message::AxisChanged(axis) => self.set_axis(axis),
+            Message::Open(None) => {}
+            Message::OverlayChannelSelected(channel) => {
+                self.keep_styles();
+                self.config.overlay_channel = channel;
+                self.overlay_chart = overlay_chart(&self.config, &self.processor);
+                self.overlay_chart.set_theme(self.config.theme);
+                self.apply_filters();
+            }
+            Message::MarkerLabelChanged(label) => self.marker_label = label,
+            Message::AddMarker(timestamp) => {
+                let Some(timestamp) = timestamp.or(self.rows.last().map(|r| r.timestamp)) else {
+                    return Command::none();
+                };
+                let label = match self.marker_label.trim() {
+                    "" => format!("marker {}", self.markers.len() + 1),
+                    label => String::from(label),
+                };
+                self.status = format!("marked {label} at {:.2}s", timestamp as f64 / 1000.);
+                marker::insert(&mut self.markers, Marker { timestamp, label });
+                if self.config.source.generator.is_some() {
+                    self.status += ", not saved as the source is generated";
+                } else if let Err(e) = marker::save(&self.config.source.path, &self.markers) {
+                    self.status = format!("failed to save the markers: {e}");
+                }
+                self.overview.set_markers(&self.markers);
+                self.redraw();
+            }
+            Message::ChartStyleChanged(id, style) => {
+                if let Some(chart) = self.time_series_chart(id) {
+                    let refilter = chart.style().filtered != style.filtered;
+                    chart.set_style(style);
+                    if refilter {
+                        self.apply_filters();
+                    }
+                }
+            }
+            Message::Export(format) => {
+                let path = self.config.source.path.with_extension(match format {
+                    ExportFormat::Csv => "export.csv",
+                    ExportFormat::JsonLines => "export.jsonl",
+                });
+                let mut processor = self.export_processor();
+                let result = self.history.all().and_then(|data| {
+                    export(
+                        &data,
+                        0..=u64::MAX,
+                        &mut processor,
+                        &self.markers,
+                        format,
+                        BufWriter::new(File::create(&path)?),
+                    )
+                });
+                self.status = match result {
+                    Ok(n) => format!("exported {n} rows to {}", path.display()),
+                    Err(e) => format!("export to {} failed: {e}", path.display()),
+                };
             }
         }
         Command::none()
     }
 
     fn subscription(&self) -> Subscription<Self::Message> {
-        struct Connect;
-
-        iced::subscription::channel(std::any::TypeId::of::<Connect>(), 100, |mut x| async move {
-            let mut input_stream = stream_file(&TEST_INPUT).await;
-            let mut interval = tokio::time::interval(Duration::from_millis(1000));
-            loop {
-                tokio::select! {
-                    _ = interval.tick() => {
-                        x.send(Message::Tick).await.unwrap();
-                    },
-                    Some(data) = input_stream.next() => {
-                        x.send(Message::ReceivedNewData(data)).await.unwrap();
-                    },
-                };
+        let sources: Vec<Subscription<Message>> = self
+            .config
+            .sources()
+            .enumerate()
+            .map(|(device, source)| source_subscription(device, source, self.replay))
+            .collect();
+        let ticks = iced::time::every(Duration::from_millis(1000)).map(|_| Message::Tick);
+        let frames = iced::time::every(Duration::from_millis(1000 / self.config.frame_rate_hz))
+            .map(Message::Frame);
+        let dropped = iced::subscription::events_with(|event, _| match event {
+            iced::Event::Window(iced::window::Event::FileDropped(path)) => {
+                Some(Message::Open(Some(path)))
             }
-        })
+            _ => None,
+        });
+        // Ctrl+M marks the latest sample
+        let hotkeys = iced::subscription::events_with(|event, status| match (event, status) {
+            (
+                iced::Event::Keyboard(keyboard::Event::KeyPressed {
+                    key_code: keyboard::KeyCode::M,
+                    modifiers,
+                }),
+                iced::event::Status::Ignored,
+            ) if modifiers.control() => Some(Message::AddMarker(None)),
+            _ => None,
+        });
+        Subscription::batch(sources.into_iter().chain([ticks, frames, dropped, hotkeys]))
     }
 }
 
+/// Samples of the `device` streamed from its recording or generator,
+/// restarted for every `replay`
+fn source_subscription(device: usize, source: &SourceConfig, replay: u64) -> Subscription<Message> {
+    struct Connect;
+
+    let path = source.path.clone();
+    let columns = source.columns.clone();
+    let generator = source.generator.clone();
+    iced::subscription::channel(
+        (
+            std::any::TypeId::of::<Connect>(),
+            device,
+            path.clone(),
+            generator.as_ref().map(|g| g.seed),
+            replay,
+        ),
+        100,
+        move |mut x| async move {
+            let mut input_stream: Pin<Box<dyn Stream<Item = _> + Send>> = match generator {
+                Some(generator) => Box::pin(generator::stream(generator)),
+                None => Box::pin(stream_file(&path, columns).await),
+            };
+            while let Some(first) = input_stream.next().await {
+                let received = Instant::now();
+                let mut batch = vec![first];
+                while let Some(Some(item)) = input_stream.next().now_or_never() {
+                    batch.push(item);
+                }
+                x.send(Message::ReceivedBatch(device, received, batch))
+                    .await
+                    .unwrap();
+            }
+            x.send(Message::SourceEnded(device, replay)).await.unwrap();
+            iced::futures::future::pending().await
+        },
+    )
+}
+
+/// [`Config::overlay_channel`] of every device, styled as configured
+fn overlay_chart(config: &Config, processor: &Processor) -> TimeSeriesChart {
+    let channel = processor
+        .channels()
+        .iter()
+        .position(|c| c.name == config.overlay_channel)
+        .expect("configuration is validated on load");
+    let series = config
+        .sources()
+        .enumerate()
+        .map(|(i, source)| Series::raw(&source.device, channel, i).on_device(i))
+        .collect();
+    let chart = config.chart(ChartId::Overlay);
+    let title = chart
+        .title
+        .unwrap_or_else(|| format!("{} of every device", config.overlay_channel));
+    let mut overlay = TimeSeriesChart::new(&title, series);
+    overlay.set_style(chart.style);
+    overlay
+}
+
 ///
 /// TEST
 /// MY CHART
@@ -241,7 +1500,7 @@ impl Chart<Message> for MyChart {
             .draw_series(
                 DATA1
                     .iter()
-                    .map(|point| TriangleMarker::new(*point, 5, &BLUE)),
+                    .map(|point| TriangleMarker::new(*point, 5, BLUE)),
             )
             .unwrap();
 
@@ -250,11 +1509,11 @@ impl Chart<Message> for MyChart {
         chart
             .draw_series(
                 AreaSeries::new(
-                    (0..).zip(data.iter().map(|x| *x)), // The data iter
-                    0,                                  // Baseline
-                    &RED.mix(0.2),                      // Make the series opac
+                    (0..).zip(data.iter().copied()), // The data iter
+                    0,                               // Baseline
+                    RED.mix(0.2),                    // Make the series opac
                 )
-                .border_style(&RED), // Make a brighter border
+                .border_style(RED), // Make a brighter border
             )
             .unwrap();
 
@@ -287,7 +1546,7 @@ impl Chart<Message> for MyChart {
 }
 
 impl MyChart {
-    fn view(&self) -> Element<Message> {
+    fn view(&self) -> Element<'_, Message> {
         let chart = ChartWidget::new(self)
             .height(Length::Fill)
             .width(Length::Fill);
@@ -307,7 +1566,6 @@ impl Default for MyChart {
 ///
 /// MY3DCHART
 ///
-
 struct My3DChart {
     cache: Cache,
 }
@@ -356,7 +1614,7 @@ impl Chart<Message> for My3DChart {
 }
 
 impl My3DChart {
-    fn view(&self) -> Element<Message> {
+    fn view(&self) -> Element<'_, Message> {
         let chart = ChartWidget::new(self)
             .height(Length::Fill)
             .width(Length::Fill);
@@ -0,0 +1,81 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Labelled point of a recording, like "engine start"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Marker {
+    pub timestamp: u64,
+    pub label: String,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Sidecar {
+    #[serde(default)]
+    marker: Vec<Marker>,
+}
+
+/// `flight.csv` keeps its markers in `flight.markers.toml`
+pub fn sidecar_path(recording: &impl AsRef<Path>) -> PathBuf {
+    recording.as_ref().with_extension("markers.toml")
+}
+
+/// Markers of `recording` sorted by timestamp, none when it has no sidecar
+pub fn load(recording: &impl AsRef<Path>) -> Result<Vec<Marker>, String> {
+    let path = sidecar_path(recording);
+    let sidecar: Sidecar = match fs::read_to_string(&path) {
+        Ok(text) => toml::from_str(&text).map_err(|e| format!("{}: {e}", path.display()))?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Sidecar::default(),
+        Err(e) => return Err(format!("{}: {e}", path.display())),
+    };
+    let mut markers = sidecar.marker;
+    markers.sort_by_key(|m| m.timestamp);
+    Ok(markers)
+}
+
+pub fn save(recording: &impl AsRef<Path>, markers: &[Marker]) -> Result<(), String> {
+    let path = sidecar_path(recording);
+    let sidecar = Sidecar {
+        marker: markers.to_vec(),
+    };
+    let text = toml::to_string_pretty(&sidecar).map_err(|e| e.to_string())?;
+    fs::write(&path, text).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Insert `marker` keeping `markers` sorted by timestamp
+pub fn insert(markers: &mut Vec<Marker>, marker: Marker) {
+    let i = markers.partition_point(|m| m.timestamp <= marker.timestamp);
+    markers.insert(i, marker);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sidecar() {
+        let recording = std::env::temp_dir().join("aeroplot-marker-test.csv");
+        let mut markers = vec![];
+        for (timestamp, label) in [(2000, "takeoff"), (500, "engine start"), (2000, "gear up")] {
+            insert(
+                &mut markers,
+                Marker {
+                    timestamp,
+                    label: String::from(label),
+                },
+            );
+        }
+        save(&recording, &markers).unwrap();
+
+        let loaded = load(&recording).unwrap();
+        let labels: Vec<&str> = loaded.iter().map(|m| m.label.as_str()).collect();
+        assert_eq!(labels, ["engine start", "takeoff", "gear up"]);
+        std::fs::remove_file(sidecar_path(&recording)).unwrap();
+        assert!(load(&recording).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,290 @@
+use std::{cell::RefCell, io};
+
+use iced::{
+    event::Status,
+    mouse::{self, Cursor},
+    widget::canvas::{Cache, Event, Frame, Geometry},
+    Element, Length, Rectangle, Size,
+};
+use plotters::prelude::ChartBuilder;
+use plotters_iced::{plotters_backend::DrawingBackend, Chart, ChartWidget, Renderer};
+
+use super::Message;
+use crate::{
+    calibration::Calibration, clock::format_wall_clock, config::SourceConfig, downsample::min_max,
+    generic::MIN_SPAN_S, marker::Marker, style::AppTheme,
+};
+
+/// buckets the whole recording is decimated to
+const TRACE_BUCKETS: usize = 1000;
+
+/// Accelerometer magnitude of the whole recording of `source`, in session
+/// time, aligned and decimated to [`TRACE_BUCKETS`]
+pub fn trace(source: &SourceConfig, calibration: &Calibration) -> io::Result<Vec<(f64, f64)>> {
+    let points: Vec<(f64, f64)> = source
+        .read(&source.path)?
+        .iter()
+        .map(|d| {
+            let d = calibration.apply(d);
+            (d.timestamp as f64 / 1000., d.acc.magnitude())
+        })
+        .collect();
+    let range = match (points.first(), points.last()) {
+        (Some(first), Some(last)) => (first.0, last.0),
+        _ => return Ok(points),
+    };
+    Ok(min_max(&points, range, TRACE_BUCKETS))
+}
+
+/// Strip with the whole recording, dragging a brush on it selects the time
+/// range of the time series charts. A click without dragging clears it.
+pub struct Overview {
+    cache: Cache,
+    /// (seconds, magnitude)
+    trace: Vec<(f64, f64)>,
+    /// selected (start, end) in ms
+    selection: Option<(u64, u64)>,
+    /// timestamp of the last sample replayed
+    latest: u64,
+    /// marker timestamps in ms
+    markers: Vec<u64>,
+    /// wall-clock ms since the epoch at time 0
+    origin: Option<u64>,
+    theme: AppTheme,
+}
+
+/// Pixel and time range of the plot, set when drawn
+#[derive(Clone, Copy)]
+struct StripArea {
+    px: (f32, f32),
+    x: (f64, f64),
+}
+
+/// Brush being dragged, from the x and time it started at
+#[derive(Default)]
+pub struct BrushState {
+    start: Option<(f32, f64)>,
+    area: RefCell<Option<StripArea>>,
+}
+
+impl BrushState {
+    /// Seconds under `x`, clamped to the plot
+    fn time_at(&self, x: f32) -> Option<f64> {
+        let StripArea { px, x: range } = (*self.area.borrow())?;
+        let f = ((x - px.0) / (px.1 - px.0).max(1.)).clamp(0., 1.) as f64;
+        Some(range.0 + f * (range.1 - range.0))
+    }
+}
+
+impl Overview {
+    pub fn new(theme: AppTheme) -> Self {
+        Self {
+            cache: Cache::new(),
+            trace: vec![],
+            selection: None,
+            latest: 0,
+            markers: vec![],
+            origin: None,
+            theme,
+        }
+    }
+
+    pub fn set_trace(&mut self, trace: Vec<(f64, f64)>) {
+        self.trace = trace;
+        self.cache.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trace.is_empty()
+    }
+
+    pub fn set_selection(&mut self, selection: Option<(u64, u64)>) {
+        self.selection = selection;
+        self.cache.clear();
+    }
+
+    pub fn set_latest(&mut self, latest: u64) {
+        self.latest = latest;
+        self.cache.clear();
+    }
+
+    pub fn set_markers(&mut self, markers: &[Marker]) {
+        self.markers = markers.iter().map(|m| m.timestamp).collect();
+        self.cache.clear();
+    }
+
+    pub fn set_origin(&mut self, origin: Option<u64>) {
+        self.origin = origin;
+        self.cache.clear();
+    }
+
+    pub fn set_theme(&mut self, theme: AppTheme) {
+        self.theme = theme;
+        self.cache.clear();
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        ChartWidget::new(self)
+            .width(Length::Fill)
+            .height(Length::Fixed(100.))
+            .into()
+    }
+}
+
+impl Chart<Message> for Overview {
+    type State = BrushState;
+
+    fn update(
+        &self,
+        state: &mut Self::State,
+        event: Event,
+        bounds: Rectangle,
+        cursor: Cursor,
+    ) -> (Status, Option<Message>) {
+        let Event::Mouse(event) = event else {
+            return (Status::Ignored, None);
+        };
+        let position = cursor.position_in(bounds);
+        match (event, state.start) {
+            (mouse::Event::ButtonPressed(mouse::Button::Left), _) => {
+                let Some(t) = position.and_then(|p| state.time_at(p.x)) else {
+                    return (Status::Ignored, None);
+                };
+                state.start = position.map(|p| (p.x, t));
+                (Status::Captured, None)
+            }
+            (mouse::Event::CursorMoved { .. }, Some((x0, start))) => {
+                // the brush follows the cursor outside of the strip too
+                let x = cursor.position().map(|p| p.x - bounds.x);
+                match x
+                    .filter(|x| (x - x0).abs() >= 1.)
+                    .and_then(|x| state.time_at(x))
+                {
+                    Some(t) => (Status::Captured, Some(brush(start, t))),
+                    None => (Status::Captured, None),
+                }
+            }
+            (mouse::Event::ButtonReleased(mouse::Button::Left), Some((x0, start))) => {
+                state.start = None;
+                // less than a pixel is a click
+                let x = cursor.position().map(|p| p.x - bounds.x);
+                let message = match x
+                    .filter(|x| (x - x0).abs() >= 1.)
+                    .and_then(|x| state.time_at(x))
+                {
+                    Some(t) => brush(start, t),
+                    None => Message::RangeSelected(None),
+                };
+                (Status::Captured, Some(message))
+            }
+            _ => (Status::Ignored, None),
+        }
+    }
+
+    fn mouse_interaction(
+        &self,
+        state: &Self::State,
+        bounds: Rectangle,
+        cursor: Cursor,
+    ) -> mouse::Interaction {
+        if state.start.is_some() || cursor.is_over(bounds) {
+            mouse::Interaction::ResizingHorizontally
+        } else {
+            mouse::Interaction::Idle
+        }
+    }
+
+    #[inline]
+    fn draw<R: Renderer, F: Fn(&mut Frame)>(
+        &self,
+        renderer: &R,
+        bounds: Size,
+        draw_fn: F,
+    ) -> Geometry {
+        renderer.draw_cache(&self.cache, bounds, draw_fn)
+    }
+
+    fn build_chart<DB: DrawingBackend>(&self, state: &Self::State, mut builder: ChartBuilder<DB>) {
+        use plotters::prelude::*;
+
+        let (Some(first), Some(last)) = (self.trace.first(), self.trace.last()) else {
+            return;
+        };
+        let x_range = (first.0, last.0.max(first.0 + 0.001));
+        let y_max = self.trace.iter().fold(0.01, |m: f64, p| m.max(p.1)) * 1.1;
+        let colors = self.theme.chart();
+        let mut chart = builder
+            .margin(5)
+            .x_label_area_size(20)
+            .build_cartesian_2d(x_range.0..x_range.1, 0.0..y_max)
+            .unwrap();
+        let wall_clock =
+            |x: &f64| format_wall_clock(self.origin.unwrap_or(0) + (x.max(0.) * 1000.) as u64);
+        let mut mesh = chart.configure_mesh();
+        mesh.disable_y_mesh()
+            .disable_y_axis()
+            .axis_style(colors.text)
+            .bold_line_style(colors.mesh.mix(0.2))
+            .light_line_style(colors.mesh.mix(0.05))
+            .label_style(("sans-serif", 12).into_font().color(&colors.text));
+        if self.origin.is_some() {
+            mesh.x_label_formatter(&wall_clock);
+        }
+        mesh.draw().unwrap();
+        let (px, _) = chart.plotting_area().get_pixel_range();
+        *state.area.borrow_mut() = Some(StripArea {
+            px: (px.start as f32, px.end as f32),
+            x: x_range,
+        });
+
+        let line = crate::style::Palette::Classic.colors(self.theme)[3];
+        chart
+            .draw_series(LineSeries::new(self.trace.iter().copied(), line))
+            .unwrap();
+        if let Some((start, end)) = self.selection {
+            chart
+                .draw_series(std::iter::once(Rectangle::new(
+                    [(start as f64 / 1000., 0.), (end as f64 / 1000., y_max)],
+                    colors.text.mix(0.2).filled(),
+                )))
+                .unwrap();
+        }
+        chart
+            .draw_series(self.markers.iter().map(|t| {
+                let t = *t as f64 / 1000.;
+                PathElement::new(vec![(t, 0.), (t, y_max)], colors.text.mix(0.7))
+            }))
+            .unwrap();
+        let latest = self.latest as f64 / 1000.;
+        chart
+            .draw_series(std::iter::once(PathElement::new(
+                vec![(latest, 0.), (latest, y_max)],
+                RED.stroke_width(2),
+            )))
+            .unwrap();
+    }
+}
+
+/// Selection between two times in seconds, in either order, at least
+/// [`MIN_SPAN_S`] wide
+fn brush(a: f64, b: f64) -> Message {
+    let start = a.min(b).max(0.);
+    let end = a.max(b).max(start + MIN_SPAN_S);
+    Message::RangeSelected(Some(((start * 1000.) as u64, (end * 1000.) as u64)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_brush_has_a_width() {
+        let range = |a, b| match brush(a, b) {
+            Message::RangeSelected(range) => range,
+            _ => unreachable!(),
+        };
+        assert_eq!(range(2.5, 1.), Some((1000, 2500)));
+        assert_eq!(range(3., 3.), Some((3000, 3100)));
+        assert_eq!(range(-1., 0.01), Some((0, 100)));
+    }
+}
@@ -0,0 +1,144 @@
+use std::io::{self, Write};
+
+use crate::{
+    alarm::{AlarmEngine, AlarmEvent},
+    datasource::{Data, Resampled, Resampler},
+    derived::Processor,
+    marker::Marker,
+    stats::RunningStats,
+};
+
+/// Plain text summary of a recording for sessions without the UI: the time
+/// span, statistics of every channel, alarms, motion events and markers.
+/// `data` goes through `resampler` first, as when it is replayed, so gaps
+/// raise their alarms too.
+pub fn report<W: Write>(
+    data: &[Data],
+    resampler: &mut Resampler,
+    processor: &mut Processor,
+    alarms: &mut AlarmEngine,
+    markers: &[Marker],
+    mut out: W,
+) -> io::Result<()> {
+    let channels = processor.channels().to_vec();
+    let mut stats = vec![RunningStats::default(); channels.len()];
+    let mut events: Vec<AlarmEvent> = vec![];
+    let mut motion = vec![];
+    for r in data.iter().flat_map(|d| resampler.push(d.clone())) {
+        let d = match r {
+            Resampled::Data(d) => d,
+            Resampled::Event(e) => {
+                events.extend(alarms.quality_event(&e));
+                continue;
+            }
+        };
+        let row = processor.process(&d);
+        for (s, v) in stats.iter_mut().zip(&row.values) {
+            if v.is_finite() {
+                s.push(*v);
+            }
+        }
+        events.extend(alarms.process(&row));
+        for event in processor.take_motion_events() {
+            events.extend(event.as_alarm());
+            motion.push(event);
+        }
+    }
+    // an alarm is reported when it starts and again when it ends
+    let ended: Vec<(String, u64)> = events
+        .iter()
+        .filter(|e| e.end.is_some())
+        .map(|e| (e.rule.clone(), e.start))
+        .collect();
+    events.retain(|e| e.end.is_some() || !ended.contains(&(e.rule.clone(), e.start)));
+
+    let seconds = |ms: u64| ms as f64 / 1000.;
+    match (data.first(), data.last()) {
+        (Some(first), Some(last)) => writeln!(
+            out,
+            "samples: {}, {:.3}s - {:.3}s",
+            data.len(),
+            seconds(first.timestamp),
+            seconds(last.timestamp)
+        )?,
+        _ => writeln!(out, "samples: 0")?,
+    }
+
+    writeln!(
+        out,
+        "\n{:<16} {:<6} {:>10} {:>10} {:>10} {:>10} {:>10}",
+        "channel", "unit", "min", "max", "mean", "std", "rms"
+    )?;
+    for (channel, s) in channels.iter().zip(&stats) {
+        let s = s.summary();
+        writeln!(
+            out,
+            "{:<16} {:<6} {:>10.4} {:>10.4} {:>10.4} {:>10.4} {:>10.4}",
+            channel.name, channel.unit, s.min, s.max, s.mean, s.std_dev, s.rms
+        )?;
+    }
+
+    writeln!(out, "\nalarms: {}", events.len())?;
+    for event in &events {
+        writeln!(out, "  {event}")?;
+    }
+    writeln!(out, "\nmotion events: {}", motion.len())?;
+    for event in &motion {
+        writeln!(out, "  {event}")?;
+    }
+    writeln!(out, "\nmarkers: {}", markers.len())?;
+    for m in markers {
+        writeln!(out, "  {:.3}s {}", seconds(m.timestamp), m.label)?;
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        alarm::{AlarmRule, Condition},
+        calibration::Calibration,
+        datasource::Interpolation,
+    };
+
+    #[test]
+    fn test_report_lists_gaps_and_markers() {
+        // 1.6s without samples after 0.4s
+        let data: Vec<Data> = [0, 100, 200, 300, 400, 2000, 2100]
+            .into_iter()
+            .map(|timestamp| Data {
+                timestamp,
+                ..Default::default()
+            })
+            .collect();
+        let mut resampler = Resampler::new(None, Interpolation::Linear, 500);
+        let mut processor = Processor::new(Calibration::default());
+        let gap = AlarmRule {
+            name: String::from("data gap"),
+            channel: String::new(),
+            condition: Condition::GapLongerThan(1000),
+            hysteresis: 0.,
+            min_duration_ms: 0,
+        };
+        let mut alarms = AlarmEngine::new(vec![gap], processor.channels()).unwrap();
+        let markers = [Marker {
+            timestamp: 250,
+            label: String::from("engine start"),
+        }];
+        let mut out = vec![];
+        report(
+            &data,
+            &mut resampler,
+            &mut processor,
+            &mut alarms,
+            &markers,
+            &mut out,
+        )
+        .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("samples: 7, 0.000s - 2.100s"));
+        assert!(text.contains("\nalarms: 1\n  0.400s - 2.000s data gap (1600.000)\n"));
+        assert!(text.ends_with("markers: 1\n  0.250s engine start\n"));
+    }
+}
@@ -0,0 +1,306 @@
+use std::{collections::VecDeque, fmt::Display};
+
+use iced::{
+    alignment::Horizontal,
+    widget::{column, image, image::Handle, row, text},
+    Alignment, ContentFit, Element, Length,
+};
+use plotters::prelude::{ColorMap, ViridisRGB};
+
+use super::Message;
+use crate::{
+    clock::format_wall_clock,
+    derived::Row,
+    generic::TimeAxis,
+    marker::Marker,
+    spectrum::{spectrum, Window},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpectrogramSource {
+    X,
+    Y,
+    Z,
+    Magnitude,
+}
+
+impl SpectrogramSource {
+    pub const ALL: [SpectrogramSource; 4] = [
+        SpectrogramSource::X,
+        SpectrogramSource::Y,
+        SpectrogramSource::Z,
+        SpectrogramSource::Magnitude,
+    ];
+
+    fn value(&self, x: f64, y: f64, z: f64) -> f64 {
+        match self {
+            SpectrogramSource::X => x,
+            SpectrogramSource::Y => y,
+            SpectrogramSource::Z => z,
+            SpectrogramSource::Magnitude => (x * x + y * y + z * z).sqrt(),
+        }
+    }
+}
+
+impl Display for SpectrogramSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpectrogramSource::X => write!(f, "X"),
+            SpectrogramSource::Y => write!(f, "Y"),
+            SpectrogramSource::Z => write!(f, "Z"),
+            SpectrogramSource::Magnitude => write!(f, "magnitude"),
+        }
+    }
+}
+
+/// Scrolling time/frequency view, one column every `hop` samples once a
+/// whole FFT length has arrived.
+///
+/// Columns are written once into a ring of pixels as the samples arrive,
+/// the history is never recomputed or redrawn through plotters.
+pub struct Spectrogram {
+    title: String,
+    /// row channels of the x, y and z axes
+    channels: [usize; 3],
+    source: SpectrogramSource,
+    /// FFT length in samples
+    samples: usize,
+    /// samples between columns
+    hop: usize,
+    /// samples pushed since the last column
+    pushed: usize,
+    /// history length in columns
+    columns: usize,
+    /// power mapped onto the colour scale, in dB
+    db_range: (f64, f64),
+    history: VecDeque<(u64, f64)>,
+    /// RGBA, row major, row 0 is the highest frequency
+    pixels: Vec<u8>,
+    /// time of the newest sample of each column of the ring
+    times: Vec<Option<u64>>,
+    next_column: usize,
+    /// a column was painted since the last redraw
+    changed: bool,
+    max_frequency: f64,
+    handle: Handle,
+}
+
+impl Spectrogram {
+    pub fn new(
+        title: &str,
+        channels: [usize; 3],
+        samples: usize,
+        hop: usize,
+        columns: usize,
+    ) -> Self {
+        let mut s = Self {
+            title: String::from(title),
+            channels,
+            source: SpectrogramSource::Magnitude,
+            samples,
+            hop: hop.max(1),
+            pushed: 0,
+            columns,
+            db_range: (-80., 0.),
+            history: VecDeque::with_capacity(samples),
+            pixels: vec![],
+            times: vec![],
+            next_column: 0,
+            changed: false,
+            max_frequency: 0.,
+            handle: Handle::from_pixels(1, 1, vec![0, 0, 0, 255]),
+        };
+        s.clear();
+        s
+    }
+
+    fn rows(&self) -> usize {
+        self.samples / 2 + 1
+    }
+
+    pub fn source(&self) -> SpectrogramSource {
+        self.source
+    }
+
+    /// Switch the displayed axis, the history is cleared
+    pub fn set_source(&mut self, source: SpectrogramSource) {
+        self.source = source;
+        self.clear();
+    }
+
+    pub fn clear(&mut self) {
+        self.history.clear();
+        self.pushed = 0;
+        self.pixels = [0, 0, 0, 255].repeat(self.columns * self.rows());
+        self.times = vec![None; self.columns];
+        self.next_column = 0;
+        self.changed = true;
+        self.redraw();
+    }
+
+    /// Adds the calibrated accelerometer values of `row`
+    pub fn push_row(&mut self, row: &Row) {
+        let [x, y, z] = self.channels.map(|c| row.values[c]);
+        if self.history.len() == self.samples {
+            self.history.pop_front();
+        }
+        self.history
+            .push_back((row.timestamp, self.source.value(x, y, z)));
+        self.pushed += 1;
+        // a shorter history has fewer bins, at other frequencies
+        if self.history.len() < self.samples || self.pushed < self.hop {
+            return;
+        }
+        self.pushed = 0;
+
+        let Some(s) = spectrum(self.history.make_contiguous(), Window::Hann) else {
+            return;
+        };
+        self.max_frequency = s.frequencies.last().copied().unwrap_or_default();
+
+        let rows = self.rows();
+        let (min_db, max_db) = self.db_range;
+        let color_map = ViridisRGB {};
+        for (bin, magnitude) in s.magnitudes.iter().enumerate().take(rows) {
+            let db = 20. * magnitude.max(1e-12).log10();
+            let color = color_map.get_color_normalized(db as f32, min_db as f32, max_db as f32);
+            let row = rows - 1 - bin;
+            let i = (row * self.columns + self.next_column) * 4;
+            self.pixels[i..i + 4].copy_from_slice(&[color.0, color.1, color.2, 255]);
+        }
+        self.times[self.next_column] = Some(row.timestamp);
+        self.next_column = (self.next_column + 1) % self.columns;
+        self.changed = true;
+    }
+
+    /// Show the columns pushed so far, rotating the ring so the oldest
+    /// column is on the left. Only rebuilt when a column was painted.
+    pub fn redraw(&mut self) {
+        if !self.changed {
+            return;
+        }
+        self.changed = false;
+        let row_len = self.columns * 4;
+        let split = self.next_column * 4;
+        let mut ordered = Vec::with_capacity(self.pixels.len());
+        for row in self.pixels.chunks(row_len) {
+            ordered.extend_from_slice(&row[split..]);
+            ordered.extend_from_slice(&row[..split]);
+        }
+        self.handle = Handle::from_pixels(self.columns as u32, self.rows() as u32, ordered);
+    }
+
+    /// Time of the `column`th displayed column, 0 is the oldest
+    fn column_time(&self, column: usize) -> Option<u64> {
+        self.times[(self.next_column + column) % self.columns]
+    }
+
+    /// Times of the oldest, middle and newest columns, as on the time axis
+    /// of the other charts
+    fn time_labels(&self, origin: Option<u64>) -> [String; 3] {
+        [0, self.columns / 2, self.columns - 1].map(|column| {
+            self.column_time(column)
+                .map(|t| time_label(t, origin))
+                .unwrap_or_default()
+        })
+    }
+
+    pub fn view(&self, axis: &TimeAxis, markers: &[Marker]) -> Element<'_, Message> {
+        let [oldest, middle, newest] = self.time_labels(axis.origin);
+        let label = |s: String, horizontal| {
+            text(s)
+                .size(12)
+                .width(Length::Fill)
+                .horizontal_alignment(horizontal)
+        };
+
+        // the cursor and markers within the shown columns
+        let mut caption = format!(
+            "0 - {:.2}Hz, {} - {}dB",
+            self.max_frequency, self.db_range.0, self.db_range.1
+        );
+        let shown = (0..self.columns).find_map(|c| self.column_time(c));
+        if let (Some(first), Some(last)) = (shown, self.column_time(self.columns - 1)) {
+            let within = |t: u64| (first..=last).contains(&t);
+            let cursor = axis.cursor.map(|c| (c.max(0.) * 1000.) as u64);
+            if let Some(cursor) = cursor.filter(|c| within(*c)) {
+                caption += &format!(", cursor {}", time_label(cursor, axis.origin));
+            }
+            for marker in markers.iter().filter(|m| within(m.timestamp)) {
+                let at = time_label(marker.timestamp, axis.origin);
+                caption += &format!(", {} at {at}", marker.label);
+            }
+        }
+
+        column![
+            text(format!("{} ({})", self.title, self.source)).size(20),
+            image(self.handle.clone())
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .content_fit(ContentFit::Fill),
+            row![
+                label(oldest, Horizontal::Left),
+                label(middle, Horizontal::Center),
+                label(newest, Horizontal::Right),
+            ],
+            text(caption).size(15),
+        ]
+        .align_items(Alignment::Center)
+        .into()
+    }
+}
+
+/// `ms` as the wall clock when the origin is known, in seconds otherwise
+fn time_label(ms: u64, origin: Option<u64>) -> String {
+    match origin {
+        Some(origin) => format_wall_clock(origin + ms),
+        None => format!("{:.1}s", ms as f64 / 1000.),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::TAU;
+
+    use super::*;
+
+    #[test]
+    fn test_tone_lands_in_its_row() {
+        let mut spectrogram = Spectrogram::new("test", [0, 1, 2], 64, 16, 10);
+        spectrogram.set_source(SpectrogramSource::X);
+        // 25Hz at 100Hz, bin 16 of 64 samples
+        for i in 0..64 + 16 {
+            let t = i as f64 / 100.;
+            let x = (TAU * 25. * t).sin();
+            spectrogram.push_row(&Row {
+                timestamp: i * 10,
+                values: vec![x, 0., 0.],
+            });
+            if i == 62 {
+                assert_eq!(
+                    spectrogram.next_column, 0,
+                    "painted before the history was full"
+                );
+            }
+        }
+        assert_eq!(spectrogram.next_column, 2);
+        assert_eq!(
+            spectrogram.time_labels(None),
+            [String::new(), String::new(), String::from("0.8s")]
+        );
+        assert_eq!(spectrogram.time_labels(Some(0))[2], "00:00:00.790");
+        spectrogram.redraw();
+        assert!(!spectrogram.changed, "rebuilt only after a new column");
+
+        let rows = spectrogram.rows();
+        let brightness = |row: usize| {
+            let i = row * spectrogram.columns * 4;
+            spectrogram.pixels[i..i + 3]
+                .iter()
+                .map(|c| *c as u32)
+                .sum::<u32>()
+        };
+        let brightest = (0..rows).max_by_key(|row| brightness(*row)).unwrap();
+        assert_eq!(brightest, rows - 1 - 16);
+    }
+}
@@ -0,0 +1,132 @@
+use std::{f64::consts::PI, fmt::Display};
+
+use rustfft::{num_complex::Complex, FftPlanner};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Window {
+    Hann,
+    Hamming,
+}
+
+impl Window {
+    pub const ALL: [Window; 2] = [Window::Hann, Window::Hamming];
+
+    fn coefficient(&self, i: usize, n: usize) -> f64 {
+        let phase = 2. * PI * i as f64 / (n - 1) as f64;
+        match self {
+            Window::Hann => 0.5 - 0.5 * phase.cos(),
+            Window::Hamming => 0.54 - 0.46 * phase.cos(),
+        }
+    }
+}
+
+impl Display for Window {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Window::Hann => write!(f, "Hann"),
+            Window::Hamming => write!(f, "Hamming"),
+        }
+    }
+}
+
+/// One sided amplitude spectrum
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Spectrum {
+    /// Hz
+    pub frequencies: Vec<f64>,
+    /// same unit as the input
+    pub magnitudes: Vec<f64>,
+}
+
+impl Spectrum {
+    /// Up to `count` local maxima, highest first, as (frequency, magnitude)
+    pub fn peaks(&self, count: usize) -> Vec<(f64, f64)> {
+        let m = &self.magnitudes;
+        let mut peaks: Vec<(f64, f64)> = (1..m.len().saturating_sub(1))
+            .filter(|&i| m[i] > m[i - 1] && m[i] >= m[i + 1])
+            .map(|i| (self.frequencies[i], m[i]))
+            .collect();
+        peaks.sort_by(|a, b| b.1.total_cmp(&a.1));
+        peaks.truncate(count);
+        peaks
+    }
+}
+
+/// Spread the unevenly spaced `samples` (timestamp in ms, value) over `n`
+/// evenly spaced points between the first and last timestamp
+fn resample(samples: &[(u64, f64)], n: usize) -> Vec<f64> {
+    let start = samples[0].0 as f64;
+    let step = (samples[samples.len() - 1].0 as f64 - start) / (n - 1) as f64;
+    let mut j = 0;
+    (0..n)
+        .map(|i| {
+            let t = start + step * i as f64;
+            while j + 2 < samples.len() && (samples[j + 1].0 as f64) < t {
+                j += 1;
+            }
+            let (t0, v0) = (samples[j].0 as f64, samples[j].1);
+            let (t1, v1) = (samples[j + 1].0 as f64, samples[j + 1].1);
+            if t1 == t0 {
+                v1
+            } else {
+                v0 + (v1 - v0) * ((t - t0) / (t1 - t0)).clamp(0., 1.)
+            }
+        })
+        .collect()
+}
+
+/// Windowed FFT of the samples, resampled to an even spacing first.
+/// The mean is removed so gravity doesn't swamp the vibration peaks.
+///
+/// Returns `None` for fewer than 4 samples or a zero time span.
+pub fn spectrum(samples: &[(u64, f64)], window: Window) -> Option<Spectrum> {
+    let n = samples.len();
+    if n < 4 {
+        return None;
+    }
+    let span = samples[n - 1].0.saturating_sub(samples[0].0) as f64 / 1000.;
+    if span <= 0. {
+        return None;
+    }
+    let rate = (n - 1) as f64 / span;
+
+    let values = resample(samples, n);
+    let mean = values.iter().sum::<f64>() / n as f64;
+    let coefficients: Vec<f64> = (0..n).map(|i| window.coefficient(i, n)).collect();
+    let mut buffer: Vec<Complex<f64>> = values
+        .iter()
+        .zip(&coefficients)
+        .map(|(v, w)| Complex::new((v - mean) * w, 0.))
+        .collect();
+    FftPlanner::new().plan_fft_forward(n).process(&mut buffer);
+
+    // amplitude correction for the window and the dropped negative half
+    let gain = 2. / coefficients.iter().sum::<f64>();
+    let bins = n / 2 + 1;
+    Some(Spectrum {
+        frequencies: (0..bins).map(|i| i as f64 * rate / n as f64).collect(),
+        magnitudes: buffer[..bins].iter().map(|c| c.norm() * gain).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spectrum_finds_tone_with_jitter() {
+        // 2Hz tone sampled at ~10Hz with uneven spacing
+        let samples: Vec<(u64, f64)> = (0..128u64)
+            .map(|i| {
+                let t = i * 100 + (i * 7) % 5;
+                (t, 1. + 0.5 * (2. * PI * 2. * t as f64 / 1000.).sin())
+            })
+            .collect();
+        let s = spectrum(&samples, Window::Hann).unwrap();
+        let peaks = s.peaks(1);
+        assert!((peaks[0].0 - 2.).abs() < 0.1, "{peaks:?}");
+        assert!((peaks[0].1 - 0.5).abs() < 0.1, "{peaks:?}");
+        // mean removed
+        assert!(s.magnitudes[0] < 0.05, "{}", s.magnitudes[0]);
+    }
+}
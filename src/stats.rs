@@ -0,0 +1,234 @@
+use std::collections::VecDeque;
+
+use crate::datasource::Data;
+
+/// Names of the axes tracked by [`Statistics`], in order
+pub const AXES: [&str; 6] = ["acc_x", "acc_y", "acc_z", "mag_x", "mag_y", "mag_z"];
+
+/// Summary of a set of values, NaN when there are none
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub rms: f64,
+}
+
+impl Summary {
+    fn new(count: usize, min: f64, max: f64, sum: f64, sum_sq: f64) -> Self {
+        if count == 0 {
+            return Self {
+                count,
+                min: f64::NAN,
+                max: f64::NAN,
+                mean: f64::NAN,
+                std_dev: f64::NAN,
+                rms: f64::NAN,
+            };
+        }
+        let n = count as f64;
+        let mean = sum / n;
+        Self {
+            count,
+            min,
+            max,
+            mean,
+            std_dev: (sum_sq / n - mean * mean).max(0.).sqrt(),
+            rms: (sum_sq / n).sqrt(),
+        }
+    }
+}
+
+/// Statistics over every value pushed
+#[derive(Debug, Clone, Default)]
+pub struct RunningStats {
+    count: usize,
+    min: f64,
+    max: f64,
+    /// Welford's running mean and sum of squared differences
+    mean: f64,
+    m2: f64,
+    sum_sq: f64,
+}
+
+impl RunningStats {
+    pub fn push(&mut self, value: f64) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        }
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+        self.sum_sq += value * value;
+    }
+
+    pub fn summary(&self) -> Summary {
+        let mut s = Summary::new(
+            self.count,
+            self.min,
+            self.max,
+            self.mean * self.count as f64,
+            self.sum_sq,
+        );
+        if self.count > 0 {
+            s.mean = self.mean;
+            s.std_dev = (self.m2 / self.count as f64).sqrt();
+        }
+        s
+    }
+}
+
+/// Statistics over the values of the last `window_ms` milliseconds,
+/// updated in amortized constant time per value
+#[derive(Debug, Clone)]
+pub struct WindowStats {
+    window_ms: u64,
+    values: VecDeque<(u64, f64)>,
+    sum: f64,
+    sum_sq: f64,
+    /// monotonic queues, the front is the window minimum and maximum
+    min: VecDeque<(u64, f64)>,
+    max: VecDeque<(u64, f64)>,
+}
+
+impl WindowStats {
+    pub fn new(window_ms: u64) -> Self {
+        Self {
+            window_ms,
+            values: VecDeque::new(),
+            sum: 0.,
+            sum_sq: 0.,
+            min: VecDeque::new(),
+            max: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, timestamp: u64, value: f64) {
+        self.values.push_back((timestamp, value));
+        self.sum += value;
+        self.sum_sq += value * value;
+        while self.min.back().is_some_and(|m| m.1 >= value) {
+            self.min.pop_back();
+        }
+        self.min.push_back((timestamp, value));
+        while self.max.back().is_some_and(|m| m.1 <= value) {
+            self.max.pop_back();
+        }
+        self.max.push_back((timestamp, value));
+
+        let oldest = timestamp.saturating_sub(self.window_ms);
+        while self.values.front().is_some_and(|v| v.0 < oldest) {
+            let (_, v) = self.values.pop_front().unwrap();
+            self.sum -= v;
+            self.sum_sq -= v * v;
+        }
+        while self.min.front().is_some_and(|m| m.0 < oldest) {
+            self.min.pop_front();
+        }
+        while self.max.front().is_some_and(|m| m.0 < oldest) {
+            self.max.pop_front();
+        }
+    }
+
+    pub fn summary(&self) -> Summary {
+        Summary::new(
+            self.values.len(),
+            self.min.front().map_or(f64::NAN, |m| m.1),
+            self.max.front().map_or(f64::NAN, |m| m.1),
+            self.sum,
+            self.sum_sq,
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChannelStats {
+    pub window: WindowStats,
+    pub session: RunningStats,
+}
+
+/// Per axis statistics plus sample timing of a source, fed one sample at a time
+#[derive(Debug, Clone)]
+pub struct Statistics {
+    pub axes: [ChannelStats; 6],
+    /// time between consecutive samples, in ms
+    pub interval_window: WindowStats,
+    pub interval_session: RunningStats,
+    pub dropped_lines: usize,
+    prev_timestamp: Option<u64>,
+}
+
+impl Statistics {
+    pub fn new(window_ms: u64) -> Self {
+        let channel = || ChannelStats {
+            window: WindowStats::new(window_ms),
+            session: RunningStats::default(),
+        };
+        Self {
+            axes: [
+                channel(),
+                channel(),
+                channel(),
+                channel(),
+                channel(),
+                channel(),
+            ],
+            interval_window: WindowStats::new(window_ms),
+            interval_session: RunningStats::default(),
+            dropped_lines: 0,
+            prev_timestamp: None,
+        }
+    }
+
+    pub fn push(&mut self, data: &Data) {
+        let t = data.timestamp;
+        let values = [
+            data.acc.x, data.acc.y, data.acc.z, data.mag.x, data.mag.y, data.mag.z,
+        ];
+        for (axis, value) in self.axes.iter_mut().zip(values) {
+            axis.window.push(t, value);
+            axis.session.push(value);
+        }
+        if let Some(prev) = self.prev_timestamp {
+            let interval = t.saturating_sub(prev) as f64;
+            self.interval_window.push(t, interval);
+            self.interval_session.push(interval);
+        }
+        self.prev_timestamp = Some(t);
+    }
+
+    /// Effective sample rate over the window in Hz and the interval jitter
+    /// (standard deviation) in ms
+    pub fn rate(&self) -> (f64, f64) {
+        let s = self.interval_window.summary();
+        (1000. / s.mean, s.std_dev)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_and_session() {
+        let mut window = WindowStats::new(200);
+        let mut session = RunningStats::default();
+        for (t, v) in [(0, 5.), (100, -1.), (200, 3.), (300, 4.), (400, 2.)] {
+            window.push(t, v);
+            session.push(v);
+        }
+        let w = window.summary();
+        assert_eq!((w.count, w.min, w.max, w.mean), (3, 2., 4., 3.));
+        assert!((w.rms - (29f64 / 3.).sqrt()).abs() < 1e-12);
+
+        let s = session.summary();
+        assert_eq!((s.count, s.min, s.max, s.mean), (5, -1., 5., 2.6));
+        assert!((s.std_dev - 2.0591260281974).abs() < 1e-9);
+    }
+}
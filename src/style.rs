@@ -0,0 +1,217 @@
+use std::fmt::Display;
+
+use plotters::style::RGBColor;
+use serde::{Deserialize, Serialize};
+
+/// Look of the widgets and the charts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AppTheme {
+    #[default]
+    Light,
+    Dark,
+    HighContrast,
+}
+
+/// Colours plotters draws a chart's frame with
+#[derive(Debug, Clone, Copy)]
+pub struct ChartColors {
+    pub background: RGBColor,
+    /// captions, labels, axes and the legend border
+    pub text: RGBColor,
+    pub mesh: RGBColor,
+}
+
+impl AppTheme {
+    pub const ALL: [AppTheme; 3] = [AppTheme::Light, AppTheme::Dark, AppTheme::HighContrast];
+
+    pub fn iced(self) -> iced::Theme {
+        match self {
+            AppTheme::Light => iced::Theme::Light,
+            AppTheme::Dark => iced::Theme::Dark,
+            AppTheme::HighContrast => iced::Theme::custom(iced::theme::Palette {
+                background: iced::Color::BLACK,
+                text: iced::Color::WHITE,
+                primary: iced::Color::from_rgb(1., 1., 0.),
+                success: iced::Color::from_rgb(0., 1., 0.),
+                danger: iced::Color::from_rgb(1., 0.3, 0.3),
+            }),
+        }
+    }
+
+    pub fn chart(self) -> ChartColors {
+        match self {
+            AppTheme::Light => ChartColors {
+                background: RGBColor(255, 255, 255),
+                text: RGBColor(0, 0, 0),
+                mesh: RGBColor(0, 0, 0),
+            },
+            // matches the background of iced's dark palette
+            AppTheme::Dark => ChartColors {
+                background: RGBColor(32, 34, 37),
+                text: RGBColor(220, 220, 220),
+                mesh: RGBColor(160, 160, 160),
+            },
+            AppTheme::HighContrast => ChartColors {
+                background: RGBColor(0, 0, 0),
+                text: RGBColor(255, 255, 255),
+                mesh: RGBColor(255, 255, 255),
+            },
+        }
+    }
+
+    fn is_dark(self) -> bool {
+        self != AppTheme::Light
+    }
+}
+
+impl Display for AppTheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppTheme::Light => write!(f, "light"),
+            AppTheme::Dark => write!(f, "dark"),
+            AppTheme::HighContrast => write!(f, "high contrast"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Palette {
+    #[default]
+    Classic,
+    /// Okabe-Ito, distinguishable with the common colour vision deficiencies
+    ColorBlindSafe,
+    Viridis,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 3] = [Palette::Classic, Palette::ColorBlindSafe, Palette::Viridis];
+
+    /// Colours of the x, y, z and magnitude series, lighter ones on dark themes
+    pub fn colors(self, theme: AppTheme) -> [RGBColor; 4] {
+        match self {
+            Palette::Classic if theme.is_dark() => [
+                RGBColor(255, 80, 80),
+                RGBColor(80, 255, 80),
+                RGBColor(100, 160, 255),
+                RGBColor(255, 255, 255),
+            ],
+            Palette::Classic => [
+                RGBColor(255, 0, 0),
+                RGBColor(0, 255, 0),
+                RGBColor(0, 0, 255),
+                RGBColor(0, 0, 0),
+            ],
+            Palette::ColorBlindSafe => [
+                RGBColor(230, 159, 0),
+                RGBColor(86, 180, 233),
+                RGBColor(0, 158, 115),
+                RGBColor(204, 121, 167),
+            ],
+            // the light end of the scale
+            Palette::Viridis if theme.is_dark() => [
+                RGBColor(59, 82, 139),
+                RGBColor(33, 145, 140),
+                RGBColor(94, 201, 98),
+                RGBColor(253, 231, 37),
+            ],
+            Palette::Viridis => [
+                RGBColor(68, 1, 84),
+                RGBColor(49, 104, 142),
+                RGBColor(53, 183, 121),
+                RGBColor(200, 170, 0),
+            ],
+        }
+    }
+}
+
+impl Display for Palette {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Palette::Classic => write!(f, "classic"),
+            Palette::ColorBlindSafe => write!(f, "colour-blind safe"),
+            Palette::Viridis => write!(f, "viridis"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LegendPosition {
+    UpperLeft,
+    #[default]
+    MiddleLeft,
+    LowerLeft,
+    UpperRight,
+    LowerRight,
+}
+
+impl LegendPosition {
+    pub const ALL: [LegendPosition; 5] = [
+        LegendPosition::UpperLeft,
+        LegendPosition::MiddleLeft,
+        LegendPosition::LowerLeft,
+        LegendPosition::UpperRight,
+        LegendPosition::LowerRight,
+    ];
+
+    /// Top left corner of a `size` box placed within `area`, both in pixels
+    pub fn place(self, area: (i32, i32), size: (i32, i32)) -> (i32, i32) {
+        const MARGIN: i32 = 10;
+        let x = match self {
+            LegendPosition::UpperRight | LegendPosition::LowerRight => area.0 - size.0 - MARGIN,
+            _ => MARGIN,
+        };
+        let y = match self {
+            LegendPosition::UpperLeft | LegendPosition::UpperRight => MARGIN,
+            LegendPosition::MiddleLeft => (area.1 - size.1) / 2,
+            LegendPosition::LowerLeft | LegendPosition::LowerRight => area.1 - size.1 - MARGIN,
+        };
+        (x, y)
+    }
+}
+
+impl Display for LegendPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LegendPosition::UpperLeft => write!(f, "legend upper left"),
+            LegendPosition::MiddleLeft => write!(f, "legend middle left"),
+            LegendPosition::LowerLeft => write!(f, "legend lower left"),
+            LegendPosition::UpperRight => write!(f, "legend upper right"),
+            LegendPosition::LowerRight => write!(f, "legend lower right"),
+        }
+    }
+}
+
+/// How a time series chart draws its series
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChartStyle {
+    /// add the magnitude of the x, y, z series as a fourth one
+    pub magnitude: bool,
+    /// overlay the raw series with the filter chain of the configuration
+    pub filtered: bool,
+    pub line_width: u32,
+    pub palette: Palette,
+    pub legend: LegendPosition,
+    /// time span shown until zoomed
+    pub time_range_ms: u64,
+}
+
+impl ChartStyle {
+    pub const LINE_WIDTHS: [u32; 4] = [1, 2, 3, 4];
+}
+
+impl Default for ChartStyle {
+    fn default() -> Self {
+        Self {
+            magnitude: false,
+            filtered: true,
+            line_width: 1,
+            palette: Palette::Classic,
+            legend: LegendPosition::MiddleLeft,
+            time_range_ms: 5000,
+        }
+    }
+}